@@ -0,0 +1,213 @@
+//====================================================================
+
+use ahash::AHashMap;
+use cabat::shipyard_tools::{prelude::*, UniqueTools};
+use shipyard::{AllStoragesView, EntitiesView, IntoIter, IntoWithId, Unique, View, ViewMut};
+
+use crate::{
+    images::{GifImage, ImageDirty, ImageShown, StandardImage},
+    keybinds::{Action, ActionInput},
+    layout::LayoutManager,
+    storage::TextureID,
+};
+
+//====================================================================
+
+pub(crate) struct AdjustmentsPlugin;
+
+impl Plugin for AdjustmentsPlugin {
+    fn build(self, workload_builder: &WorkloadBuilder) {
+        workload_builder
+            .add_workload(Stages::Setup, sys_setup_adjustments)
+            .add_workload(Stages::Update, sys_adjust_image);
+    }
+}
+
+//====================================================================
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Channel {
+    #[default]
+    All,
+    Red,
+    Green,
+    Blue,
+    // Alpha shown as greyscale
+    Alpha,
+}
+
+impl Channel {
+    fn next(self) -> Self {
+        match self {
+            Channel::All => Channel::Red,
+            Channel::Red => Channel::Green,
+            Channel::Green => Channel::Blue,
+            Channel::Blue => Channel::Alpha,
+            Channel::Alpha => Channel::All,
+        }
+    }
+}
+
+/// Display adjustments for an image in the viewer. Applied in the shader, the
+/// image's pixels are never touched.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageAdjustments {
+    pub brightness: f32,
+    pub contrast: f32,
+    pub gamma: f32,
+    pub invert: bool,
+    pub channel: Channel,
+}
+
+impl Default for ImageAdjustments {
+    fn default() -> Self {
+        Self {
+            brightness: 0.,
+            contrast: 1.,
+            gamma: 1.,
+            invert: false,
+            channel: Channel::All,
+        }
+    }
+}
+
+impl ImageAdjustments {
+    /// Brightness, contrast, gamma and invert packed for the shader, along with the channel
+    pub fn raw(&self) -> ([f32; 4], u32) {
+        (
+            [
+                self.brightness,
+                self.contrast,
+                self.gamma,
+                self.invert as u8 as f32,
+            ],
+            self.channel as u32,
+        )
+    }
+
+    /// Short description for the viewer overlay, empty when nothing is adjusted
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+
+        if self.brightness != 0. {
+            parts.push(format!("Brightness {:+.2}", self.brightness));
+        }
+        if self.contrast != 1. {
+            parts.push(format!("Contrast {:.1}", self.contrast));
+        }
+        if self.gamma != 1. {
+            parts.push(format!("Gamma {:.1}", self.gamma));
+        }
+        if self.invert {
+            parts.push("Inverted".to_string());
+        }
+        if self.channel != Channel::All {
+            parts.push(format!("{:?}", self.channel));
+        }
+
+        parts.join("   ")
+    }
+}
+
+//====================================================================
+
+/// Adjustments made to each image this session, keyed by texture
+#[derive(Unique)]
+pub struct Adjustments {
+    images: AHashMap<TextureID, ImageAdjustments>,
+
+    brightness_step: f32,
+    contrast_step: f32,
+    gamma_step: f32,
+}
+
+impl Adjustments {
+    #[inline]
+    pub fn get(&self, id: TextureID) -> ImageAdjustments {
+        self.images.get(&id).copied().unwrap_or_default()
+    }
+}
+
+//====================================================================
+
+fn sys_setup_adjustments(all_storages: AllStoragesView) {
+    all_storages.add_unique(Adjustments {
+        images: AHashMap::new(),
+
+        brightness_step: 0.05,
+        contrast_step: 0.1,
+        gamma_step: 0.1,
+    });
+}
+
+fn sys_adjust_image(
+    actions: ActionInput,
+    layout: Res<LayoutManager>,
+    mut adjustments: ResMut<Adjustments>,
+
+    entities: EntitiesView,
+    v_shown: View<ImageShown>,
+    v_image: View<StandardImage>,
+    v_gif: View<GifImage>,
+    mut vm_dirty: ViewMut<ImageDirty>,
+) {
+    if !layout.selected() {
+        return;
+    }
+
+    let (entity_id, id) = match (&v_shown, &v_image).iter().with_id().next() {
+        Some((entity_id, (_, image))) => (entity_id, image.id),
+        None => match (&v_shown, &v_gif).iter().with_id().next() {
+            Some((entity_id, (_, gif))) => (entity_id, gif.id),
+            None => return,
+        },
+    };
+
+    let steps = [
+        (Action::BrightnessUp, Action::BrightnessDown),
+        (Action::ContrastUp, Action::ContrastDown),
+        (Action::GammaUp, Action::GammaDown),
+    ]
+    .map(|(up, down)| actions.just_pressed(up) as i8 - actions.just_pressed(down) as i8);
+
+    let invert = actions.just_pressed(Action::InvertColors);
+    let cycle_channel = actions.just_pressed(Action::CycleChannel);
+    let reset = actions.just_pressed(Action::ResetAdjustments);
+
+    if steps == [0; 3] && !invert && !cycle_channel && !reset {
+        return;
+    }
+
+    entities.add_component(entity_id, &mut vm_dirty, ImageDirty);
+
+    if reset {
+        adjustments.images.remove(&id);
+        return;
+    }
+
+    let adjustments = &mut *adjustments;
+    let mut adjusted = adjustments.get(id);
+
+    adjusted.brightness =
+        (adjusted.brightness + steps[0] as f32 * adjustments.brightness_step).clamp(-1., 1.);
+    adjusted.contrast =
+        (adjusted.contrast + steps[1] as f32 * adjustments.contrast_step).clamp(0., 4.);
+    adjusted.gamma = (adjusted.gamma + steps[2] as f32 * adjustments.gamma_step).clamp(0.1, 5.);
+
+    // Step sizes don't add up exactly, snap back to neutral values
+    adjusted.brightness = (adjusted.brightness * 100.).round() / 100.;
+    adjusted.contrast = (adjusted.contrast * 10.).round() / 10.;
+    adjusted.gamma = (adjusted.gamma * 10.).round() / 10.;
+
+    adjusted.invert ^= invert;
+    if cycle_channel {
+        adjusted.channel = adjusted.channel.next();
+    }
+
+    match adjusted == ImageAdjustments::default() {
+        true => adjustments.images.remove(&id),
+        false => adjustments.images.insert(id, adjusted),
+    };
+}
+
+//====================================================================
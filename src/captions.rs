@@ -0,0 +1,249 @@
+//====================================================================
+
+use std::time::SystemTime;
+
+use cabat::{
+    renderer::text::{Text2dBuffer, TextFontSystem},
+    shipyard_tools::{prelude::*, UniqueTools},
+};
+use shipyard::{AllStoragesView, Component, IntoIter, IntoWorkload, Unique, ViewMut};
+
+use crate::{
+    config::{AppearanceSettings, Settings, SettingsChangedEvent, SettingsFile},
+    keybinds::{Action, ActionInput},
+    layout::{LayoutManager, RelayoutEvent},
+    storage::FileInfo,
+    tools::{civil_from_time, ellipsize_middle},
+};
+
+//====================================================================
+
+pub(crate) struct CaptionPlugin;
+
+impl Plugin for CaptionPlugin {
+    fn build(self, workload_builder: &WorkloadBuilder) {
+        workload_builder
+            .add_workload(Stages::Setup, sys_setup_captions)
+            .add_workload(
+                Stages::Update,
+                (sys_toggle_captions, sys_format_captions).into_sequential_workload(),
+            );
+    }
+}
+
+//====================================================================
+
+/// Templates the current captions were formatted with
+#[derive(Unique)]
+pub struct Captions {
+    caption: String,
+    caption_detail: String,
+}
+
+#[derive(Component)]
+pub struct ImageCaption {
+    name: String,
+    resolution: (u32, u32),
+    file_size: u64,
+    frames: u32,
+    modified: Option<SystemTime>,
+    color_converted: bool,
+
+    lines: Vec<String>,
+    // Character limit the text buffer was last shaped with
+    fitted: Option<usize>,
+}
+
+impl ImageCaption {
+    pub fn new(name: &str, info: &FileInfo, frames: u32, appearance: &AppearanceSettings) -> Self {
+        let mut caption = Self {
+            name: name.to_string(),
+            resolution: info.resolution,
+            file_size: info.file_size,
+            frames,
+            modified: info.modified,
+            color_converted: info.color_converted,
+
+            lines: Vec::new(),
+            fitted: None,
+        };
+
+        caption.format(appearance);
+        caption
+    }
+
+    #[inline]
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    fn format(&mut self, appearance: &AppearanceSettings) {
+        self.lines = [&appearance.caption, &appearance.caption_detail]
+            .into_iter()
+            .filter(|template| !template.is_empty())
+            .map(|template| self.expand(template))
+            .collect();
+
+        self.fitted = None;
+    }
+
+    /// Placeholders are expanded in a single pass, so ones that turn up in the
+    /// expanded values (a file named `{size}.png`) are left alone
+    fn expand(&self, template: &str) -> String {
+        let mut expanded = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(open) = rest.find('{') {
+            expanded.push_str(&rest[..open]);
+            rest = &rest[open..];
+
+            let placeholder = rest.find('}').map(|close| &rest[..=close]);
+            let value = placeholder.and_then(|placeholder| self.placeholder(placeholder));
+
+            match (placeholder, value) {
+                (Some(placeholder), Some(value)) => {
+                    expanded.push_str(&value);
+                    rest = &rest[placeholder.len()..];
+                }
+                // Not a placeholder, keep the brace as it is
+                _ => {
+                    expanded.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+
+        expanded.push_str(rest);
+        expanded
+    }
+
+    fn placeholder(&self, placeholder: &str) -> Option<String> {
+        let value = match placeholder {
+            "{name}" => self.name.clone(),
+            "{width}" => self.resolution.0.to_string(),
+            "{height}" => self.resolution.1.to_string(),
+            "{size}" => format_size(self.file_size),
+            "{frames}" => self.frames.to_string(),
+            "{mtime}" => match self.modified {
+                Some(modified) => {
+                    let (year, month, day, seconds) = civil_from_time(modified);
+                    format!(
+                        "{}-{:02}-{:02} {:02}:{:02} UTC",
+                        year,
+                        month,
+                        day,
+                        seconds / 3600,
+                        seconds / 60 % 60
+                    )
+                }
+                None => String::new(),
+            },
+            "{icc}" => match self.color_converted {
+                true => "ICC".to_string(),
+                false => String::new(),
+            },
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+    /// Truncate each line to fit within the given width. The text is only reshaped
+    /// when the number of characters that fit changes.
+    pub fn fit(
+        &mut self,
+        text: &mut Text2dBuffer,
+        font_system: &mut TextFontSystem,
+        width: f32,
+        font_size: f32,
+    ) {
+        // Rough average glyph width since text can't be measured before it's shaped
+        let max_chars = (width / (font_size * 0.55)).floor().max(1.) as usize;
+
+        if self.fitted == Some(max_chars) {
+            return;
+        }
+
+        self.fitted = Some(max_chars);
+
+        let fitted = self
+            .lines
+            .iter()
+            .map(|line| ellipsize_middle(line, max_chars))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        text.set_text(font_system.inner_mut(), &fitted);
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.;
+    let mut unit = 0;
+
+    while size >= 1024. && unit < UNITS.len() - 1 {
+        size /= 1024.;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+//====================================================================
+
+fn sys_setup_captions(all_storages: AllStoragesView, settings: Res<Settings>) {
+    all_storages.add_unique(Captions {
+        caption: settings.appearance.caption.clone(),
+        caption_detail: settings.appearance.caption_detail.clone(),
+    });
+}
+
+fn sys_toggle_captions(
+    mut events: ResMut<EventHandler>,
+    actions: ActionInput,
+    layout: Res<LayoutManager>,
+    mut settings: ResMut<Settings>,
+    mut settings_file: ResMut<SettingsFile>,
+) {
+    if layout.fullscreen() || !actions.just_pressed(Action::ToggleCaptions) {
+        return;
+    }
+
+    settings.appearance.show_captions = !settings.appearance.show_captions;
+    settings_file.request_save();
+
+    events.add_event(SettingsChangedEvent);
+}
+
+/// Reformat every caption when the templates are changed in the settings file
+fn sys_format_captions(
+    mut events: ResMut<EventHandler>,
+    settings: Res<Settings>,
+    mut captions: ResMut<Captions>,
+    mut vm_caption: ViewMut<ImageCaption>,
+) {
+    let appearance = &settings.appearance;
+
+    if captions.caption == appearance.caption
+        && captions.caption_detail == appearance.caption_detail
+    {
+        return;
+    }
+
+    captions.caption = appearance.caption.clone();
+    captions.caption_detail = appearance.caption_detail.clone();
+
+    (&mut vm_caption)
+        .iter()
+        .for_each(|mut caption| caption.format(appearance));
+
+    events.add_event(RelayoutEvent);
+}
+
+//====================================================================
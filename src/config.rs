@@ -0,0 +1,380 @@
+//====================================================================
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use cabat::{
+    runner::tools::Time,
+    shipyard_tools::{prelude::*, UniqueTools},
+};
+use serde::{Deserialize, Serialize};
+use shipyard::{AllStoragesView, IntoWorkload, Unique};
+
+//====================================================================
+
+pub(crate) struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn build(self, workload_builder: &WorkloadBuilder) {
+        workload_builder
+            .add_workload_pre(Stages::Setup, sys_setup_settings)
+            .add_workload(
+                Stages::Update,
+                (sys_watch_settings, sys_save_settings).into_sequential_workload(),
+            );
+    }
+}
+
+//====================================================================
+
+#[derive(Event)]
+pub(crate) struct SettingsChangedEvent;
+
+#[derive(Unique, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub layout: LayoutSettings,
+    pub navigation: NavigationSettings,
+    pub appearance: AppearanceSettings,
+    pub library: LibrarySettings,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct LayoutSettings {
+    /// Starting tile size for folders without a saved zoom. Zooming is saved per
+    /// folder and doesn't change this.
+    pub tile_size: [f32; 2],
+    pub tile_spacing: [f32; 2],
+    pub min_tile_size: [f32; 2],
+    pub max_tile_size: [f32; 2],
+    pub split_ratio: f32,
+}
+
+impl Default for LayoutSettings {
+    fn default() -> Self {
+        Self {
+            tile_size: [200., 200.],
+            tile_spacing: [10., 60.],
+            min_tile_size: [80., 80.],
+            max_tile_size: [500., 500.],
+            split_ratio: 0.5,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct NavigationSettings {
+    pub scroll_mod: f32,
+    pub move_speed: f32,
+    pub move_mod: f32,
+    pub zoom_speed: f32,
+    pub zoom_mod: f32,
+
+    pub scroll_smoothing: f32,
+    pub wheel_impulse: f32,
+    pub wheel_friction: f32,
+}
+
+impl Default for NavigationSettings {
+    fn default() -> Self {
+        Self {
+            scroll_mod: 4.,
+            move_speed: 800.,
+            move_mod: 3.,
+            zoom_speed: 120.,
+            zoom_mod: 2.1,
+
+            scroll_smoothing: 14.,
+            wheel_impulse: 1400.,
+            wheel_friction: 6.,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AppearanceSettings {
+    /// Colour used by the custom background style
+    pub background: [f32; 4],
+    pub background_style: BackgroundStyle,
+    /// Checkerboard drawn behind transparent parts of images
+    pub transparency_checker: bool,
+    pub show_captions: bool,
+    /// Caption shown under each tile. Supports `{name}`, `{width}`, `{height}`,
+    /// `{size}`, `{frames}`, `{mtime}` (in UTC) and `{icc}` (set for colour converted
+    /// images).
+    pub caption: String,
+    /// Optional second caption line, left empty to hide it
+    pub caption_detail: String,
+    pub animate_gifs: AnimateGifs,
+    pub tone_map: ToneMap,
+}
+
+impl Default for AppearanceSettings {
+    fn default() -> Self {
+        Self {
+            background: [0.1, 0.1, 0.1, 1.],
+            background_style: BackgroundStyle::default(),
+            transparency_checker: false,
+            show_captions: true,
+            caption: "{name}".to_string(),
+            caption_detail: String::new(),
+            animate_gifs: AnimateGifs::default(),
+            tone_map: ToneMap::default(),
+        }
+    }
+}
+
+/// What's drawn behind the grid and viewer. Custom uses the `background` colour.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BackgroundStyle {
+    Dark,
+    Light,
+    Checker,
+    #[default]
+    Custom,
+}
+
+impl BackgroundStyle {
+    pub fn next(self) -> Self {
+        match self {
+            BackgroundStyle::Dark => BackgroundStyle::Light,
+            BackgroundStyle::Light => BackgroundStyle::Checker,
+            BackgroundStyle::Checker => BackgroundStyle::Custom,
+            BackgroundStyle::Custom => BackgroundStyle::Dark,
+        }
+    }
+}
+
+/// Which gifs in the grid play, the rest stay on their first frame
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnimateGifs {
+    #[default]
+    All,
+    HoveredOrSelected,
+}
+
+impl AnimateGifs {
+    pub fn next(self) -> Self {
+        match self {
+            AnimateGifs::All => AnimateGifs::HoveredOrSelected,
+            AnimateGifs::HoveredOrSelected => AnimateGifs::All,
+        }
+    }
+
+    /// Frame a grid gif is held on, if any
+    pub fn held_frame(self, hovered_or_selected: bool) -> Option<u32> {
+        match self == AnimateGifs::All || hovered_or_selected {
+            true => None,
+            false => Some(0),
+        }
+    }
+}
+
+/// How high dynamic range images are brought down to the display's range
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ToneMap {
+    Reinhard,
+    #[default]
+    Aces,
+}
+
+impl ToneMap {
+    pub fn next(self) -> Self {
+        match self {
+            ToneMap::Reinhard => ToneMap::Aces,
+            ToneMap::Aces => ToneMap::Reinhard,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct LibrarySettings {
+    /// Also load images from subfolders of the opened folder
+    pub recursive: bool,
+    pub group_by: GroupBy,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    #[default]
+    None,
+    Folder,
+    Month,
+}
+
+impl GroupBy {
+    pub fn next(self) -> Self {
+        match self {
+            GroupBy::None => GroupBy::Folder,
+            GroupBy::Folder => GroupBy::Month,
+            GroupBy::Month => GroupBy::None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(crate::NAME).join("settings.toml"))
+    }
+
+    fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        toml::from_str(&data).map_err(|e| e.to_string())
+    }
+
+    fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        let data = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, data).map_err(|e| e.to_string())
+    }
+}
+
+//====================================================================
+
+#[derive(Unique)]
+pub struct SettingsFile {
+    path: Option<PathBuf>,
+    // Last modification time we've seen, either from loading or saving
+    modified: Option<SystemTime>,
+
+    poll_timer: Duration,
+    poll_interval: Duration,
+
+    // Time since the last save request. Saves are delayed so holding a zoom key
+    // doesn't write the file every frame.
+    save_timer: Option<Duration>,
+    save_delay: Duration,
+}
+
+impl SettingsFile {
+    #[inline]
+    pub fn request_save(&mut self) {
+        self.save_timer = Some(Duration::ZERO);
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        self.path
+            .as_ref()
+            .and_then(|path| std::fs::metadata(path).ok())
+            .and_then(|meta| meta.modified().ok())
+    }
+}
+
+//====================================================================
+
+fn sys_setup_settings(all_storages: AllStoragesView) {
+    let path = Settings::path();
+
+    let settings = match &path {
+        Some(path) if path.exists() => match Settings::load(path) {
+            Ok(settings) => {
+                log::info!("Loaded settings from {:?}", path);
+                settings
+            }
+            Err(e) => {
+                log::warn!("Failed to load settings from {:?}: {}", path, e);
+                Settings::default()
+            }
+        },
+
+        // Write out the defaults so there's something to edit
+        Some(path) => {
+            let settings = Settings::default();
+            if let Err(e) = settings.save(path) {
+                log::warn!("Failed to create settings file {:?}: {}", path, e);
+            }
+            settings
+        }
+
+        None => Settings::default(),
+    };
+
+    let mut file = SettingsFile {
+        path,
+        modified: None,
+
+        poll_timer: Duration::ZERO,
+        poll_interval: Duration::from_secs(1),
+
+        save_timer: None,
+        save_delay: Duration::from_secs(1),
+    };
+    file.modified = file.modified();
+
+    all_storages.insert(settings).insert(file);
+}
+
+fn sys_watch_settings(
+    mut events: ResMut<EventHandler>,
+    time: Res<Time>,
+    mut settings: ResMut<Settings>,
+    mut file: ResMut<SettingsFile>,
+) {
+    file.poll_timer += *time.delta();
+
+    if file.poll_timer < file.poll_interval {
+        return;
+    }
+
+    file.poll_timer = Duration::ZERO;
+
+    let modified = file.modified();
+    if modified.is_none() || modified == file.modified {
+        return;
+    }
+
+    file.modified = modified;
+
+    let path = file.path.as_ref().unwrap();
+
+    match Settings::load(path) {
+        Ok(new_settings) => {
+            log::info!("Reloaded settings from {:?}", path);
+            *settings = new_settings;
+            events.add_event(SettingsChangedEvent);
+        }
+        Err(e) => log::warn!("Failed to reload settings from {:?}: {}", path, e),
+    }
+}
+
+fn sys_save_settings(time: Res<Time>, settings: Res<Settings>, mut file: ResMut<SettingsFile>) {
+    let timer = match file.save_timer {
+        Some(timer) => timer + *time.delta(),
+        None => return,
+    };
+
+    if timer < file.save_delay {
+        file.save_timer = Some(timer);
+        return;
+    }
+
+    file.save_timer = None;
+
+    let path = match &file.path {
+        Some(path) => path.clone(),
+        None => return,
+    };
+
+    match settings.save(&path) {
+        Ok(_) => log::debug!("Saved settings to {:?}", path),
+        Err(e) => log::warn!("Failed to save settings to {:?}: {}", path, e),
+    }
+
+    // Don't reload our own changes
+    file.modified = file.modified();
+}
+
+//====================================================================
@@ -0,0 +1,191 @@
+//====================================================================
+
+use cabat::shipyard_tools::{prelude::*, UniqueTools};
+use shipyard::{
+    AllStoragesView, EntitiesViewMut, EntityId, Get, IntoIter, IntoWithId, Unique, View, ViewMut,
+};
+
+use crate::{
+    images::{ImageFocused, ImageHovered, ImageIndex, ImageSelected, ImageVisible, Pos},
+    layout::LayoutManager,
+    renderer::rect_pipeline::RectShape,
+};
+
+//====================================================================
+
+pub(crate) struct DecorationPlugin;
+
+impl Plugin for DecorationPlugin {
+    fn build(self, workload_builder: &WorkloadBuilder) {
+        workload_builder
+            .add_workload(Stages::Setup, sys_setup_decorations)
+            .add_workload_post(Stages::Update, sys_decorate_tiles);
+    }
+}
+
+//====================================================================
+
+#[derive(Clone, Copy)]
+struct DecorationStyle {
+    plate: [f32; 4],
+    outline: [f32; 4],
+    outline_width: f32,
+}
+
+/// Plates and outlines drawn around hovered, focused and selected tiles
+#[derive(Unique)]
+pub struct Decorations {
+    // Space between the tile and the edge of its decoration
+    padding: f32,
+    corner_radius: f32,
+
+    hovered: DecorationStyle,
+    focused: DecorationStyle,
+    selected: DecorationStyle,
+    selected_focused: DecorationStyle,
+
+    // Plate and outline entities, reused between frames
+    pool: Vec<(EntityId, EntityId)>,
+}
+
+impl Decorations {
+    fn style(&self, selected: bool, focused: bool, hovered: bool) -> Option<DecorationStyle> {
+        match (selected, focused, hovered) {
+            (true, true, _) => Some(self.selected_focused),
+            (true, false, _) => Some(self.selected),
+            (false, true, _) => Some(self.focused),
+            (false, false, true) => Some(self.hovered),
+            (false, false, false) => None,
+        }
+    }
+}
+
+//====================================================================
+
+fn sys_setup_decorations(all_storages: AllStoragesView) {
+    all_storages.add_unique(Decorations {
+        padding: 6.,
+        corner_radius: 8.,
+
+        hovered: DecorationStyle {
+            plate: [1., 1., 1., 0.06],
+            outline: [1., 1., 1., 0.25],
+            outline_width: 1.5,
+        },
+        focused: DecorationStyle {
+            plate: [1., 1., 1., 0.1],
+            outline: [1., 1., 1., 0.85],
+            outline_width: 2.,
+        },
+        selected: DecorationStyle {
+            plate: [0.3, 0.6, 1., 0.2],
+            outline: [0.3, 0.6, 1., 1.],
+            outline_width: 3.,
+        },
+        selected_focused: DecorationStyle {
+            plate: [0.3, 0.6, 1., 0.25],
+            outline: [0.65, 0.85, 1., 1.],
+            outline_width: 3.,
+        },
+
+        pool: Vec::new(),
+    });
+}
+
+fn sys_decorate_tiles(
+    layout: Res<LayoutManager>,
+    mut decorations: ResMut<Decorations>,
+
+    mut entities: EntitiesViewMut,
+    v_index: View<ImageIndex>,
+    v_visible: View<ImageVisible>,
+    v_hovered: View<ImageHovered>,
+    v_focused: View<ImageFocused>,
+    v_selected: View<ImageSelected>,
+    mut vm_pos: ViewMut<Pos>,
+    mut vm_rect: ViewMut<RectShape>,
+) {
+    let tiles = match layout.fullscreen() {
+        true => Vec::new(),
+        false => (&vm_pos, &v_index, &v_visible)
+            .iter()
+            .with_id()
+            .filter_map(|(id, (pos, _, _))| {
+                decorations
+                    .style(
+                        v_selected.contains(id),
+                        v_focused.contains(id),
+                        v_hovered.contains(id),
+                    )
+                    .map(|style| (glam::vec2(pos.x, pos.y), style))
+            })
+            .collect::<Vec<_>>(),
+    };
+
+    while decorations.pool.len() < tiles.len() {
+        // Plates sit behind the tiles and outlines in front of them
+        let plate = entities.add_entity(
+            (&mut vm_pos, &mut vm_rect),
+            (
+                Pos::default(),
+                RectShape::outline(0., 0., 0., [0., 0., 0., 0.])
+                    .with_depth(2.2)
+                    .with_corner_radius(decorations.corner_radius),
+            ),
+        );
+
+        let outline = entities.add_entity(
+            (&mut vm_pos, &mut vm_rect),
+            (
+                Pos::default(),
+                RectShape::outline(0., 0., 0., [0., 0., 0., 0.])
+                    .with_corner_radius(decorations.corner_radius),
+            ),
+        );
+
+        decorations.pool.push((plate, outline));
+    }
+
+    let size = layout.tile_size() + decorations.padding * 2.;
+
+    decorations
+        .pool
+        .iter()
+        .enumerate()
+        .for_each(|(index, (plate, outline))| {
+            let tile = tiles.get(index);
+
+            let (mut pos, mut rect) = (&mut vm_pos, &mut vm_rect).get(*plate).unwrap();
+            match tile {
+                Some((tile_pos, style)) => {
+                    pos.x = tile_pos.x;
+                    pos.y = tile_pos.y;
+                    rect.width = size.x;
+                    rect.height = size.y;
+                    rect.color = style.plate;
+                }
+                None => {
+                    rect.width = 0.;
+                    rect.height = 0.;
+                }
+            }
+
+            let (mut pos, mut rect) = (&mut vm_pos, &mut vm_rect).get(*outline).unwrap();
+            match tile {
+                Some((tile_pos, style)) => {
+                    pos.x = tile_pos.x;
+                    pos.y = tile_pos.y;
+                    rect.width = size.x;
+                    rect.height = size.y;
+                    rect.border_color = style.outline;
+                    rect.border_width = style.outline_width;
+                }
+                None => {
+                    rect.width = 0.;
+                    rect.height = 0.;
+                }
+            }
+        });
+}
+
+//====================================================================
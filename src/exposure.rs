@@ -0,0 +1,230 @@
+//====================================================================
+
+use cabat::{
+    common::WindowSize,
+    renderer::text::{Text2dBuffer, TextFontSystem},
+    runner::tools::MouseInput,
+    shipyard_tools::{prelude::*, UniqueTools},
+};
+use shipyard::{AllStoragesView, EntitiesViewMut, IntoIter, Unique, View, ViewMut};
+
+use crate::{
+    config::{Settings, SettingsFile, ToneMap},
+    images::{ImageShown, Pos, StandardImage},
+    keybinds::{Action, ActionInput},
+    layout::LayoutManager,
+    renderer::{
+        camera::UiCamera,
+        rect_pipeline::{RectShape, UiRect},
+    },
+    slider::{Slider, Track},
+    storage::{Storage, TextureType},
+};
+
+//====================================================================
+
+pub(crate) struct ExposurePlugin;
+
+impl Plugin for ExposurePlugin {
+    fn build(self, workload_builder: &WorkloadBuilder) {
+        workload_builder
+            .add_workload(Stages::Setup, sys_setup_exposure)
+            .add_workload(Stages::Update, sys_adjust_exposure)
+            .add_workload_post(Stages::Update, sys_update_exposure_slider);
+    }
+}
+
+//====================================================================
+
+/// Exposure of high dynamic range images in the viewer, in stops
+#[derive(Unique)]
+pub struct Exposure {
+    // Whether the viewer is showing a high dynamic range image
+    active: bool,
+
+    stops: f32,
+    min_stops: f32,
+    max_stops: f32,
+    step: f32,
+
+    width: f32,
+    slider: Slider,
+}
+
+impl Exposure {
+    /// Multiplier applied to the image before tone mapping
+    #[inline]
+    pub fn scale(&self) -> f32 {
+        2f32.powf(self.stops)
+    }
+
+    /// Center and size of the slider in screen space, in the top right of the viewer pane
+    fn track(&self, layout: &LayoutManager, window_size: &WindowSize) -> Track {
+        let (pane_pos, pane_size) = layout.viewer_pane(window_size);
+        let margin = self.slider.margin();
+        let width = self.width.min((pane_size.x - margin * 2.).max(0.));
+
+        (
+            glam::vec2(
+                pane_pos.x + pane_size.x / 2. - margin - width / 2.,
+                window_size.height_f32() / 2. - margin - self.slider.bar_height() / 2.,
+            ),
+            glam::vec2(width, self.slider.bar_height()),
+        )
+    }
+
+    /// Whether the given screen space point is over the slider
+    pub fn contains(
+        &self,
+        layout: &LayoutManager,
+        window_size: &WindowSize,
+        point: glam::Vec2,
+    ) -> bool {
+        self.active && self.slider.contains(self.track(layout, window_size), point)
+    }
+
+    fn set_stops(&mut self, stops: f32) {
+        self.stops = stops.clamp(self.min_stops, self.max_stops);
+    }
+}
+
+//====================================================================
+
+fn sys_setup_exposure(
+    all_storages: AllStoragesView,
+    mut entities: EntitiesViewMut,
+    mut font_system: ResMut<TextFontSystem>,
+
+    mut vm_pos: ViewMut<Pos>,
+    mut vm_rect: ViewMut<RectShape>,
+    mut vm_ui: ViewMut<UiRect>,
+    mut vm_text: ViewMut<Text2dBuffer>,
+) {
+    let slider = Slider::new(
+        &mut entities,
+        &mut font_system,
+        &mut vm_pos,
+        &mut vm_rect,
+        &mut vm_ui,
+        &mut vm_text,
+    );
+
+    all_storages.add_unique(Exposure {
+        active: false,
+
+        stops: 0.,
+        min_stops: -8.,
+        max_stops: 8.,
+        step: 0.5,
+
+        width: 200.,
+        slider,
+    });
+}
+
+fn sys_adjust_exposure(
+    window_size: Res<WindowSize>,
+    layout: Res<LayoutManager>,
+    ui_camera: Res<UiCamera>,
+    storage: Res<Storage>,
+    mut exposure: ResMut<Exposure>,
+    mut settings: ResMut<Settings>,
+    mut settings_file: ResMut<SettingsFile>,
+
+    actions: ActionInput,
+    mouse: Res<MouseInput>,
+
+    v_shown: View<ImageShown>,
+    v_image: View<StandardImage>,
+) {
+    exposure.active = layout.selected()
+        && (&v_shown, &v_image).iter().any(|(_, image)| {
+            matches!(
+                storage
+                    .get_texture(image.id)
+                    .map(|texture| &texture.texture),
+                Some(TextureType::Hdr(texture)) if texture.scene_referred
+            )
+        });
+
+    if !exposure.active {
+        exposure.slider.stop_dragging();
+        return;
+    }
+
+    if actions.just_pressed(Action::ExposureUp) {
+        let stops = exposure.stops + exposure.step;
+        exposure.set_stops(stops);
+    }
+
+    if actions.just_pressed(Action::ExposureDown) {
+        let stops = exposure.stops - exposure.step;
+        exposure.set_stops(stops);
+    }
+
+    if actions.just_pressed(Action::ExposureReset) {
+        exposure.stops = 0.;
+    }
+
+    if actions.just_pressed(Action::CycleToneMap) {
+        settings.appearance.tone_map = settings.appearance.tone_map.next();
+        settings_file.request_save();
+
+        log::info!("Tone mapping: {:?}", settings.appearance.tone_map);
+    }
+
+    let mouse_pos = ui_camera.raw.screen_to_camera(mouse.screen_pos());
+    let track = exposure.track(&layout, &window_size);
+
+    if let Some(progress) = exposure.slider.drag(track, &actions, mouse_pos) {
+        // Snap to tenths of a stop
+        let stops = exposure.min_stops + progress * (exposure.max_stops - exposure.min_stops);
+        exposure.set_stops((stops * 10.).round() / 10.);
+    }
+}
+
+fn sys_update_exposure_slider(
+    window_size: Res<WindowSize>,
+    layout: Res<LayoutManager>,
+    settings: Res<Settings>,
+    mut exposure: ResMut<Exposure>,
+    mut font_system: ResMut<TextFontSystem>,
+
+    mut vm_pos: ViewMut<Pos>,
+    mut vm_rect: ViewMut<RectShape>,
+    mut vm_text: ViewMut<Text2dBuffer>,
+) {
+    let track = exposure.track(&layout, &window_size);
+    let progress =
+        (exposure.stops - exposure.min_stops) / (exposure.max_stops - exposure.min_stops);
+
+    exposure
+        .slider
+        .update_bar(track, progress, exposure.active, &mut vm_pos, &mut vm_rect);
+
+    let text = match exposure.active {
+        true => format!(
+            "EV {:+.1}   {}",
+            exposure.stops,
+            match settings.appearance.tone_map {
+                ToneMap::Reinhard => "Reinhard",
+                ToneMap::Aces => "ACES",
+            }
+        ),
+        false => String::new(),
+    };
+
+    // Just below the slider
+    let label_y = exposure.slider.margin() + exposure.slider.bar_height() + 12.;
+
+    exposure.slider.update_label(
+        track,
+        label_y,
+        text,
+        &window_size,
+        &mut font_system,
+        &mut vm_text,
+    );
+}
+
+//====================================================================
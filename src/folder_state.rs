@@ -0,0 +1,299 @@
+//====================================================================
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use cabat::{
+    runner::tools::Time,
+    shipyard_tools::{prelude::*, UniqueTools},
+};
+use serde::{Deserialize, Serialize};
+use shipyard::{
+    AllStoragesView, EntitiesView, IntoIter, IntoWithId, IntoWorkload, Unique, View, ViewMut,
+};
+
+use crate::{
+    config::{GroupBy, Settings},
+    grouping::Grouping,
+    images::{ImageFocused, ImageIndex, ImageSelected, ImageSource},
+    layout::{set_focused, LayoutManager, RelayoutEvent, ScrollController, SelectedEvent},
+    renderer::camera::MainCamera,
+    storage::Storage,
+};
+
+//====================================================================
+
+pub(crate) struct FolderStatePlugin;
+
+impl Plugin for FolderStatePlugin {
+    fn build(self, workload_builder: &WorkloadBuilder) {
+        workload_builder
+            .add_workload(Stages::Setup, sys_setup_folder_states)
+            .add_workload(
+                Stages::Update,
+                (
+                    sys_open_folder_state,
+                    sys_restore_folder_state,
+                    sys_record_folder_state,
+                )
+                    .into_sequential_workload(),
+            );
+    }
+}
+
+//====================================================================
+
+/// Where the grid was left when a folder was last viewed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FolderState {
+    pub scroll: f32,
+    pub tile_size: [f32; 2],
+    pub group_by: GroupBy,
+    pub selected: Option<PathBuf>,
+    pub viewer_open: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct FolderStore {
+    // Keyed by canonical folder path
+    folders: HashMap<String, FolderState>,
+}
+
+impl FolderStore {
+    fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        toml::from_str(&data).map_err(|e| e.to_string())
+    }
+
+    fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        let data = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, data).map_err(|e| e.to_string())
+    }
+}
+
+/// Parts of a saved state still waiting on their images to be loaded
+struct PendingRestore {
+    scroll: Option<f32>,
+    selected: Option<(PathBuf, bool)>,
+}
+
+#[derive(Unique)]
+pub struct FolderStates {
+    path: Option<PathBuf>,
+    store: FolderStore,
+
+    folder: Option<String>,
+    pending: Option<PendingRestore>,
+
+    // Last recorded state, saved once it stops changing
+    current: Option<FolderState>,
+    save_timer: Option<Duration>,
+    save_delay: Duration,
+}
+
+impl FolderStates {
+    pub fn path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join(crate::NAME).join("folders.toml"))
+    }
+}
+
+//====================================================================
+
+fn sys_setup_folder_states(all_storages: AllStoragesView) {
+    let path = FolderStates::path();
+
+    let store = match &path {
+        Some(path) if path.exists() => FolderStore::load(path).unwrap_or_else(|e| {
+            log::warn!("Failed to load folder states from {:?}: {}", path, e);
+            FolderStore::default()
+        }),
+        _ => FolderStore::default(),
+    };
+
+    all_storages.add_unique(FolderStates {
+        path,
+        store,
+
+        folder: None,
+        pending: None,
+
+        current: None,
+        save_timer: None,
+        save_delay: Duration::from_secs(1),
+    });
+}
+
+/// Apply the saved layout for a folder as soon as it starts loading
+fn sys_open_folder_state(
+    mut events: ResMut<EventHandler>,
+    storage: Res<Storage>,
+    settings: Res<Settings>,
+    mut layout: ResMut<LayoutManager>,
+    mut grouping: ResMut<Grouping>,
+    mut states: ResMut<FolderStates>,
+) {
+    let folder = storage.canonical_root().to_string_lossy();
+
+    if folder.is_empty() || states.folder.as_deref() == Some(&*folder) {
+        return;
+    }
+
+    let folder = folder.to_string();
+    let state = states.store.folders.get(&folder).cloned();
+
+    states.folder = Some(folder);
+    states.current = None;
+    states.pending = None;
+
+    // The settings are only defaults, folders without a saved state go back to them
+    let (tile_size, group_by) = match &state {
+        Some(state) => (state.tile_size, state.group_by),
+        None => (settings.layout.tile_size, settings.library.group_by),
+    };
+
+    layout.set_tile_size(glam::Vec2::from(tile_size));
+    grouping.set_group_by(group_by);
+    events.add_event(RelayoutEvent);
+
+    let state = match state {
+        Some(state) => state,
+        None => return,
+    };
+
+    log::info!("Restoring folder state {:?}", state);
+
+    states.pending = Some(PendingRestore {
+        scroll: Some(state.scroll),
+        selected: state.selected.map(|path| (path, state.viewer_open)),
+    });
+}
+
+fn sys_restore_folder_state(
+    mut events: ResMut<EventHandler>,
+    storage: Res<Storage>,
+    layout: Res<LayoutManager>,
+    mut scroll: ResMut<ScrollController>,
+    mut states: ResMut<FolderStates>,
+
+    entities: EntitiesView,
+    v_index: View<ImageIndex>,
+    v_source: View<ImageSource>,
+    mut vm_focused: ViewMut<ImageFocused>,
+    mut vm_selected: ViewMut<ImageSelected>,
+) {
+    let pending = match &mut states.pending {
+        Some(pending) => pending,
+        None => return,
+    };
+
+    let loading = storage.loading();
+
+    if let Some(target) = pending.scroll {
+        // Wait until enough rows exist to scroll that far
+        if !loading || layout.scroll_bounds().0 <= target {
+            scroll.jump_to(target);
+            pending.scroll = None;
+        }
+    }
+
+    if let Some((path, viewer_open)) = &pending.selected {
+        let image = (&v_index, &v_source)
+            .iter()
+            .with_id()
+            .find(|(_, (_, source))| &source.path == path)
+            .map(|(id, _)| id);
+
+        if let Some(id) = image {
+            set_focused(&entities, &mut vm_focused, id);
+
+            vm_selected.clear();
+            entities.add_component(id, &mut vm_selected, ImageSelected);
+
+            if *viewer_open {
+                events.add_event(SelectedEvent {
+                    selected: Some(id),
+                    fade: None,
+                });
+            }
+        }
+
+        if image.is_some() || !loading {
+            pending.selected = None;
+        }
+    }
+
+    if pending.scroll.is_none() && pending.selected.is_none() {
+        states.pending = None;
+    }
+}
+
+fn sys_record_folder_state(
+    time: Res<Time>,
+    grouping: Res<Grouping>,
+    layout: Res<LayoutManager>,
+    camera: Res<MainCamera>,
+    mut states: ResMut<FolderStates>,
+
+    v_index: View<ImageIndex>,
+    v_source: View<ImageSource>,
+    v_focused: View<ImageFocused>,
+) {
+    // Don't overwrite the saved state before it's been restored
+    let folder = match (&states.folder, &states.pending) {
+        (Some(folder), None) => folder.clone(),
+        _ => return,
+    };
+
+    let selected = (&v_index, &v_source, &v_focused)
+        .iter()
+        .next()
+        .map(|(_, source, _)| source.path.clone());
+
+    let state = FolderState {
+        scroll: camera.raw.translation.y,
+        tile_size: layout.tile_size().to_array(),
+        group_by: grouping.group_by(),
+        selected,
+        viewer_open: layout.selected(),
+    };
+
+    if states.current.as_ref() != Some(&state) {
+        states.current = Some(state);
+        states.save_timer = Some(Duration::ZERO);
+        return;
+    }
+
+    let timer = match states.save_timer {
+        Some(timer) => timer + *time.delta(),
+        None => return,
+    };
+
+    if timer < states.save_delay {
+        states.save_timer = Some(timer);
+        return;
+    }
+
+    states.save_timer = None;
+
+    let state = states.current.clone().unwrap();
+    states.store.folders.insert(folder, state);
+
+    let path = match &states.path {
+        Some(path) => path.clone(),
+        None => return,
+    };
+
+    match states.store.save(&path) {
+        Ok(_) => log::debug!("Saved folder state to {:?}", path),
+        Err(e) => log::warn!("Failed to save folder state to {:?}: {}", path, e),
+    }
+}
+
+//====================================================================
@@ -0,0 +1,343 @@
+//====================================================================
+
+use std::{cmp::Reverse, collections::HashSet, path::PathBuf};
+
+use cabat::{
+    common::WindowSize,
+    renderer::text::{Text2dBuffer, Text2dBufferDescriptor, TextFontSystem},
+    runner::tools::MouseInput,
+    shipyard_tools::{prelude::*, UniqueTools},
+};
+use shipyard::{
+    AllStoragesView, EntitiesViewMut, EntityId, Get, IntoIter, IntoWithId, IntoWorkload, Unique,
+    View, ViewMut,
+};
+
+use crate::{
+    config::{GroupBy, Settings, SettingsFile},
+    images::{ImageIndex, ImageSource, Pos, ToRemove},
+    keybinds::{Action, ActionInput},
+    layout::{Group, GroupHeader, LayoutManager, RelayoutEvent},
+    renderer::camera::{MainCamera, UiCamera},
+    scrollbar::Scrollbar,
+    storage::Storage,
+};
+
+//====================================================================
+
+pub(crate) struct GroupingPlugin;
+
+impl Plugin for GroupingPlugin {
+    fn build(self, workload_builder: &WorkloadBuilder) {
+        workload_builder
+            .add_workload(Stages::Setup, sys_setup_grouping)
+            .add_workload(
+                Stages::Update,
+                (sys_cycle_grouping, sys_regroup_images, sys_toggle_groups)
+                    .into_sequential_workload(),
+            );
+    }
+}
+
+//====================================================================
+
+#[derive(Unique)]
+pub struct Grouping {
+    // What the grid should be grouped by. Follows the setting unless a folder's
+    // saved state overrides it.
+    group_by: GroupBy,
+    setting: GroupBy,
+
+    // What the grid is currently grouped by and how many images that covered
+    grouped_by: GroupBy,
+    image_count: u32,
+
+    // Titles of collapsed groups, kept so regrouping new images doesn't expand them
+    collapsed: HashSet<String>,
+    headers: Vec<EntityId>,
+}
+
+impl Grouping {
+    #[inline]
+    pub fn group_by(&self) -> GroupBy {
+        self.group_by
+    }
+
+    /// Group this folder differently to the setting, without changing it
+    #[inline]
+    pub fn set_group_by(&mut self, group_by: GroupBy) {
+        self.group_by = group_by;
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum GroupKey {
+    None,
+    Folder(PathBuf),
+    // Newest months first
+    Month(Reverse<(i32, u32)>),
+    UnknownMonth,
+}
+
+impl GroupKey {
+    fn new(group_by: GroupBy, source: &ImageSource) -> Self {
+        match group_by {
+            GroupBy::None => GroupKey::None,
+            GroupBy::Folder => GroupKey::Folder(source.folder.clone()),
+            GroupBy::Month => match source.month {
+                Some(month) => GroupKey::Month(Reverse(month)),
+                None => GroupKey::UnknownMonth,
+            },
+        }
+    }
+
+    fn title(&self, storage: &Storage) -> String {
+        const MONTHS: [&str; 12] = [
+            "January",
+            "February",
+            "March",
+            "April",
+            "May",
+            "June",
+            "July",
+            "August",
+            "September",
+            "October",
+            "November",
+            "December",
+        ];
+
+        match self {
+            GroupKey::None => String::new(),
+
+            // Images directly in the opened folder are titled with its name
+            GroupKey::Folder(folder) => match folder.as_os_str().is_empty() {
+                true => storage
+                    .root()
+                    .file_name()
+                    .unwrap_or(storage.root().as_os_str())
+                    .to_string_lossy()
+                    .to_string(),
+                false => folder.to_string_lossy().to_string(),
+            },
+
+            GroupKey::Month(Reverse((year, month))) => {
+                match month
+                    .checked_sub(1)
+                    .and_then(|month| MONTHS.get(month as usize))
+                {
+                    Some(name) => format!("{} {}", name, year),
+                    None => "Unknown date".to_string(),
+                }
+            }
+
+            GroupKey::UnknownMonth => "Unknown date".to_string(),
+        }
+    }
+}
+
+#[inline]
+fn header_text(group: &Group) -> String {
+    let marker = match group.collapsed {
+        true => "[+]",
+        false => "[-]",
+    };
+
+    format!("{} {}  ({})", marker, group.title, group.len)
+}
+
+//====================================================================
+
+fn sys_setup_grouping(all_storages: AllStoragesView, settings: Res<Settings>) {
+    all_storages.add_unique(Grouping {
+        group_by: settings.library.group_by,
+        setting: settings.library.group_by,
+
+        grouped_by: GroupBy::None,
+        image_count: 0,
+
+        collapsed: HashSet::new(),
+        headers: Vec::new(),
+    });
+}
+
+fn sys_cycle_grouping(
+    actions: ActionInput,
+    layout: Res<LayoutManager>,
+    mut grouping: ResMut<Grouping>,
+    mut settings: ResMut<Settings>,
+    mut settings_file: ResMut<SettingsFile>,
+) {
+    if layout.fullscreen() || !actions.just_pressed(Action::CycleGrouping) {
+        return;
+    }
+
+    // Cycling also makes the new grouping the default for other folders
+    grouping.group_by = grouping.group_by.next();
+    settings.library.group_by = grouping.group_by;
+    settings_file.request_save();
+
+    log::info!("Grouping images by {:?}", grouping.group_by);
+}
+
+/// Sort the grid by group whenever the grouping changes or new images are loaded
+fn sys_regroup_images(
+    mut events: ResMut<EventHandler>,
+    settings: Res<Settings>,
+    storage: Res<Storage>,
+    mut layout: ResMut<LayoutManager>,
+    mut grouping: ResMut<Grouping>,
+    mut font_system: ResMut<TextFontSystem>,
+
+    mut entities: EntitiesViewMut,
+    v_source: View<ImageSource>,
+    mut vm_index: ViewMut<ImageIndex>,
+    mut vm_pos: ViewMut<Pos>,
+    mut vm_header: ViewMut<GroupHeader>,
+    mut vm_text: ViewMut<Text2dBuffer>,
+    mut vm_remove: ViewMut<ToRemove>,
+) {
+    // Changes to the setting apply over any folder's saved grouping
+    if settings.library.group_by != grouping.setting {
+        grouping.setting = settings.library.group_by;
+        grouping.group_by = settings.library.group_by;
+    }
+
+    let group_by = grouping.group_by;
+
+    let changed = group_by != grouping.grouped_by;
+    let loaded = layout.image_count() != grouping.image_count;
+
+    grouping.grouped_by = group_by;
+    grouping.image_count = layout.image_count();
+
+    // Ungrouped images are already in load order
+    if !changed && (!loaded || group_by == GroupBy::None) {
+        return;
+    }
+
+    let mut images = (&vm_index, &v_source)
+        .iter()
+        .with_id()
+        .map(|(id, (_, source))| (id, GroupKey::new(group_by, source), source.order))
+        .collect::<Vec<_>>();
+
+    images.sort_by(|(_, key_a, order_a), (_, key_b, order_b)| {
+        key_a.cmp(key_b).then(order_a.cmp(order_b))
+    });
+
+    let mut groups: Vec<Group> = Vec::new();
+    let mut last_key = None;
+
+    images.iter().enumerate().for_each(|(index, (id, key, _))| {
+        (&mut vm_index).get(*id).unwrap().index = index as u32;
+
+        if group_by == GroupBy::None {
+            return;
+        }
+
+        match groups.last_mut() {
+            Some(group) if last_key == Some(key) => group.len += 1,
+            _ => {
+                let title = key.title(&storage);
+
+                groups.push(Group {
+                    collapsed: grouping.collapsed.contains(&title),
+                    title,
+                    start: index as u32,
+                    len: 1,
+                });
+                last_key = Some(key);
+            }
+        }
+    });
+
+    // Reuse header entities where possible and remove any left over
+    let reused = groups.len().min(grouping.headers.len());
+    let stale = grouping.headers.split_off(reused);
+    stale
+        .into_iter()
+        .for_each(|id| entities.add_component(id, &mut vm_remove, ToRemove));
+
+    groups.iter().enumerate().for_each(|(index, group)| {
+        let text = header_text(group);
+
+        match grouping.headers.get(index) {
+            Some(id) => (&mut vm_text)
+                .get(*id)
+                .unwrap()
+                .set_text(font_system.inner_mut(), &text),
+
+            None => {
+                let id = entities.add_entity(
+                    (&mut vm_pos, &mut vm_header, &mut vm_text),
+                    (
+                        Pos::default(),
+                        GroupHeader { group: index },
+                        Text2dBuffer::new(
+                            font_system.inner_mut(),
+                            &Text2dBufferDescriptor::new_text(&text),
+                        ),
+                    ),
+                );
+                grouping.headers.push(id);
+            }
+        }
+    });
+
+    layout.set_groups(groups);
+    events.add_event(RelayoutEvent);
+}
+
+fn sys_toggle_groups(
+    mut events: ResMut<EventHandler>,
+    actions: ActionInput,
+    mouse: Res<MouseInput>,
+
+    window_size: Res<WindowSize>,
+    main_camera: Res<MainCamera>,
+    ui_camera: Res<UiCamera>,
+    scrollbar: Res<Scrollbar>,
+    mut layout: ResMut<LayoutManager>,
+    mut grouping: ResMut<Grouping>,
+
+    mut font_system: ResMut<TextFontSystem>,
+    mut vm_text: ViewMut<Text2dBuffer>,
+) {
+    if !actions.just_pressed(Action::Select) {
+        return;
+    }
+
+    let ui_mouse_pos = ui_camera.raw.screen_to_camera(mouse.screen_pos());
+    if scrollbar.contains(&layout, &window_size, ui_mouse_pos) {
+        return;
+    }
+
+    let mouse_pos = main_camera.raw.screen_to_camera(mouse.screen_pos());
+
+    let index = match layout.header_at(&window_size, mouse_pos) {
+        Some(index) => index,
+        None => return,
+    };
+
+    layout.toggle_group(index);
+
+    let group = &layout.groups()[index];
+
+    match group.collapsed {
+        true => grouping.collapsed.insert(group.title.clone()),
+        false => grouping.collapsed.remove(&group.title),
+    };
+
+    if let Some(mut text) = grouping
+        .headers
+        .get(index)
+        .and_then(|id| (&mut vm_text).get(*id).ok())
+    {
+        text.set_text(font_system.inner_mut(), &header_text(group));
+    }
+
+    events.add_event(RelayoutEvent);
+}
+
+//====================================================================
@@ -1,6 +1,6 @@
 //====================================================================
 
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
 
 use cabat::{common::Size, shipyard_tools::prelude::*};
 use shipyard::{
@@ -10,9 +10,7 @@ use shipyard::{
 // use shipyard_shared::Size;
 // use shipyard_tools::{Plugin, Stages};
 
-use crate::renderer::{
-    gif::GifFrameDelay, gif2d_pipeline::Gif2dInstance, texture2d_pipeline::Texture2dInstance,
-};
+use crate::storage::TextureID;
 
 //====================================================================
 
@@ -22,7 +20,7 @@ impl Plugin for ImagePlugin {
     fn build(self, workload_builder: &WorkloadBuilder) {
         workload_builder.add_workload(
             Stages::Last,
-            (sys_remove_pending, sys_clear_dirty).into_workload(),
+            (sys_clear_dirty, sys_remove_pending).into_sequential_workload(),
         );
     }
 }
@@ -77,6 +75,7 @@ impl Default for ImageSize {
 #[derive(Component)]
 pub struct ImageMeta {
     pub texture_resolution: Size<u32>,
+    pub color_converted: bool,
 }
 
 //--------------------------------------------------
@@ -118,40 +117,43 @@ pub struct Image;
 
 #[derive(Component)]
 pub struct StandardImage {
-    pub id: u64,
-    pub instance: Texture2dInstance,
+    pub id: TextureID,
 }
 
 #[derive(Component)]
 pub struct GifImage {
-    pub id: u64,
-    pub frame: u32,
-    pub total_frames: u32,
-    pub frames_per_row: u32,
-    pub instance: Gif2dInstance,
+    pub id: TextureID,
+    // Frame to hold on, otherwise the gif follows the shared clock
+    pub frame: Option<u32>,
 }
 
 #[derive(Component)]
-pub struct GifTimer {
-    pub acc: Duration,
-    pub delay: GifFrameDelay,
+pub struct ImageIndex {
+    pub index: u32,
 }
 
+/// Where an image came from, used to group the grid
 #[derive(Component)]
-pub struct ImageIndex {
-    pub index: u32,
+pub struct ImageSource {
+    pub path: PathBuf,
+    pub folder: PathBuf,
+    pub month: Option<(i32, u32)>,
+    // Index the image was loaded at
+    pub order: u32,
 }
 
 #[derive(Component)]
 pub struct ImageDirty;
 
-// TODO / OPTIMIZE
-// #[derive(Component)]
-// pub struct ImageVisible;
+#[derive(Component)]
+pub struct ImageVisible;
 
 #[derive(Component)]
 pub struct ImageHovered;
 
+#[derive(Component)]
+pub struct ImageFocused;
+
 #[derive(Component)]
 pub struct ImageSelected;
 
@@ -159,6 +161,34 @@ pub struct ImageSelected;
 #[derive(Component)]
 pub struct ImageShown;
 
+/// Fades a shown image in or out by animating its alpha
+#[derive(Component)]
+pub struct ImageFade {
+    pub elapsed: Duration,
+    pub duration: Duration,
+    pub out: bool,
+}
+
+impl ImageFade {
+    #[inline]
+    pub fn fade_in(duration: Duration) -> Self {
+        Self {
+            elapsed: Duration::ZERO,
+            duration,
+            out: false,
+        }
+    }
+
+    #[inline]
+    pub fn fade_out(duration: Duration) -> Self {
+        Self {
+            elapsed: Duration::ZERO,
+            duration,
+            out: true,
+        }
+    }
+}
+
 //====================================================================
 
 #[derive(Component)]
@@ -199,7 +229,6 @@ pub struct ImageCreator<'v> {
     pub gif_image: ViewMut<'v, GifImage>,
     pub meta: ViewMut<'v, ImageMeta>,
 
-    pub gif_timer: ViewMut<'v, GifTimer>,
     pub dirty: ViewMut<'v, ImageDirty>,
 }
 
@@ -236,12 +265,7 @@ impl ImageCreator<'_> {
         )
     }
 
-    pub fn spawn_gif(
-        &mut self,
-        gif: GifImage,
-        frame_delay: &Vec<Duration>,
-        meta: ImageMeta,
-    ) -> EntityId {
+    pub fn spawn_gif(&mut self, gif: GifImage, meta: ImageMeta) -> EntityId {
         self.entities.add_entity(
             (
                 &mut self.image,
@@ -249,7 +273,6 @@ impl ImageCreator<'_> {
                 &mut self.size,
                 &mut self.color,
                 &mut self.gif_image,
-                &mut self.gif_timer,
                 &mut self.meta,
                 &mut self.dirty,
             ),
@@ -259,10 +282,6 @@ impl ImageCreator<'_> {
                 ImageSize::default(),
                 Color::default(),
                 gif,
-                GifTimer {
-                    acc: Duration::default(),
-                    delay: GifFrameDelay::from_durations(frame_delay),
-                },
                 meta,
                 ImageDirty,
             ),
@@ -281,9 +300,16 @@ fn sys_remove_pending(mut all_storages: AllStoragesViewMut) {
         .map(|(id, _)| id)
         .collect::<Vec<_>>();
 
+    if ids.is_empty() {
+        return;
+    }
+
     ids.into_iter().for_each(|id| {
         all_storages.delete_entity(id);
     });
+
+    // Cleared before this runs, so the pipelines drop the deleted images next frame
+    all_storages.run(|mut image_dirtier: ImageDirtier| image_dirtier.mark_all_dirty());
 }
 
 fn sys_clear_dirty(mut vm_dirty: ViewMut<ImageDirty>) {
@@ -0,0 +1,490 @@
+//====================================================================
+
+use std::{collections::HashMap, path::PathBuf};
+
+use cabat::{
+    runner::tools::{Input, KeyCode, MouseButton},
+    shipyard_tools::{prelude::*, UniqueTools},
+};
+use serde::Deserialize;
+use shipyard::{AllStoragesView, Borrow, BorrowInfo, Unique, UniqueView};
+
+//====================================================================
+
+pub(crate) struct KeybindsPlugin;
+
+impl Plugin for KeybindsPlugin {
+    fn build(self, workload_builder: &WorkloadBuilder) {
+        workload_builder.add_workload_pre(Stages::Setup, sys_setup_keymap);
+    }
+}
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    // Grid
+    ScrollUp,
+    ScrollDown,
+    ZoomIn,
+    ZoomOut,
+    // Held to speed up scrolling and zooming
+    FastModifier,
+    // Held to zoom the grid with the mouse wheel
+    WheelZoomModifier,
+
+    FocusLeft,
+    FocusRight,
+    FocusUp,
+    FocusDown,
+    FocusNext,
+    FocusPrev,
+    OpenFocused,
+    ToggleFullscreen,
+    // Switch between no grouping, grouping by folder and grouping by month
+    CycleGrouping,
+    ToggleCaptions,
+    CycleBackground,
+    ToggleTransparencyChecker,
+    // Switch between playing every gif in the grid and only hovered or selected ones
+    ToggleAnimateGifs,
+
+    // Selection
+    Select,
+    // Held while selecting to toggle images or add to the selection
+    ToggleSelectModifier,
+    // Held while selecting to select a range of images
+    RangeSelectModifier,
+    Deselect,
+    CloseViewer,
+
+    // Viewer
+    ViewerActualSize,
+    ViewerToggleFill,
+    ViewerPan,
+
+    // Gif playback in the viewer
+    GifPlayPause,
+    GifStepForward,
+    GifStepBack,
+    GifFaster,
+    GifSlower,
+
+    // High dynamic range images in the viewer
+    ExposureUp,
+    ExposureDown,
+    ExposureReset,
+    CycleToneMap,
+
+    // Display adjustments for the image in the viewer
+    BrightnessUp,
+    BrightnessDown,
+    ContrastUp,
+    ContrastDown,
+    GammaUp,
+    GammaDown,
+    InvertColors,
+    // Show all channels, then red, green, blue and alpha on their own
+    CycleChannel,
+    ResetAdjustments,
+
+    // Slideshow
+    SlideshowToggle,
+    SlideshowPause,
+    SlideshowShuffle,
+    SlideshowLoop,
+    SlideshowCrossfade,
+    SlideshowFaster,
+    SlideshowSlower,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Trigger {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+/// A trigger along with the modifiers that must be held with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Chord {
+    ctrl: bool,
+    shift: bool,
+    alt: bool,
+    trigger: Trigger,
+}
+
+impl Chord {
+    #[inline]
+    fn key(key: KeyCode) -> Self {
+        Self {
+            ctrl: false,
+            shift: false,
+            alt: false,
+            trigger: Trigger::Key(key),
+        }
+    }
+
+    #[inline]
+    fn mouse(button: MouseButton) -> Self {
+        Self {
+            ctrl: false,
+            shift: false,
+            alt: false,
+            trigger: Trigger::Mouse(button),
+        }
+    }
+
+    #[inline]
+    fn with_ctrl(mut self) -> Self {
+        self.ctrl = true;
+        self
+    }
+
+    #[inline]
+    fn with_shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    /// Parse chords like `W`, `Ctrl+Shift+K` or `Alt+MouseLeft`
+    fn parse(chord: &str) -> Option<Self> {
+        let mut parts = chord.split('+').map(str::trim).collect::<Vec<_>>();
+        let trigger = parse_trigger(parts.pop()?)?;
+
+        let mut parsed = Self {
+            ctrl: false,
+            shift: false,
+            alt: false,
+            trigger,
+        };
+
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => parsed.ctrl = true,
+                "shift" => parsed.shift = true,
+                "alt" => parsed.alt = true,
+                _ => return None,
+            }
+        }
+
+        Some(parsed)
+    }
+
+    /// Modifiers must match exactly, so `K` doesn't fire along with `Ctrl+K`.
+    /// Modifier keys bound on their own are held alongside other chords and skip this.
+    fn modifiers_held(&self, keys: &Input<KeyCode>) -> bool {
+        if let Trigger::Key(
+            KeyCode::ControlLeft
+            | KeyCode::ControlRight
+            | KeyCode::ShiftLeft
+            | KeyCode::ShiftRight
+            | KeyCode::AltLeft
+            | KeyCode::AltRight,
+        ) = self.trigger
+        {
+            return true;
+        }
+
+        let held = |left, right| keys.pressed(left) || keys.pressed(right);
+
+        self.ctrl == held(KeyCode::ControlLeft, KeyCode::ControlRight)
+            && self.shift == held(KeyCode::ShiftLeft, KeyCode::ShiftRight)
+            && self.alt == held(KeyCode::AltLeft, KeyCode::AltRight)
+    }
+}
+
+fn parse_trigger(trigger: &str) -> Option<Trigger> {
+    let button = match trigger {
+        "MouseLeft" => Some(MouseButton::Left),
+        "MouseRight" => Some(MouseButton::Right),
+        "MouseMiddle" => Some(MouseButton::Middle),
+        _ => None,
+    };
+
+    if let Some(button) = button {
+        return Some(Trigger::Mouse(button));
+    }
+
+    let key = match trigger {
+        "A" | "KeyA" => KeyCode::KeyA,
+        "B" | "KeyB" => KeyCode::KeyB,
+        "C" | "KeyC" => KeyCode::KeyC,
+        "D" | "KeyD" => KeyCode::KeyD,
+        "E" | "KeyE" => KeyCode::KeyE,
+        "F" | "KeyF" => KeyCode::KeyF,
+        "G" | "KeyG" => KeyCode::KeyG,
+        "H" | "KeyH" => KeyCode::KeyH,
+        "I" | "KeyI" => KeyCode::KeyI,
+        "J" | "KeyJ" => KeyCode::KeyJ,
+        "K" | "KeyK" => KeyCode::KeyK,
+        "L" | "KeyL" => KeyCode::KeyL,
+        "M" | "KeyM" => KeyCode::KeyM,
+        "N" | "KeyN" => KeyCode::KeyN,
+        "O" | "KeyO" => KeyCode::KeyO,
+        "P" | "KeyP" => KeyCode::KeyP,
+        "Q" | "KeyQ" => KeyCode::KeyQ,
+        "R" | "KeyR" => KeyCode::KeyR,
+        "S" | "KeyS" => KeyCode::KeyS,
+        "T" | "KeyT" => KeyCode::KeyT,
+        "U" | "KeyU" => KeyCode::KeyU,
+        "V" | "KeyV" => KeyCode::KeyV,
+        "W" | "KeyW" => KeyCode::KeyW,
+        "X" | "KeyX" => KeyCode::KeyX,
+        "Y" | "KeyY" => KeyCode::KeyY,
+        "Z" | "KeyZ" => KeyCode::KeyZ,
+        "0" | "Digit0" => KeyCode::Digit0,
+        "1" | "Digit1" => KeyCode::Digit1,
+        "2" | "Digit2" => KeyCode::Digit2,
+        "3" | "Digit3" => KeyCode::Digit3,
+        "4" | "Digit4" => KeyCode::Digit4,
+        "5" | "Digit5" => KeyCode::Digit5,
+        "6" | "Digit6" => KeyCode::Digit6,
+        "7" | "Digit7" => KeyCode::Digit7,
+        "8" | "Digit8" => KeyCode::Digit8,
+        "9" | "Digit9" => KeyCode::Digit9,
+        "F1" => KeyCode::F1,
+        "F2" => KeyCode::F2,
+        "F3" => KeyCode::F3,
+        "F4" => KeyCode::F4,
+        "F5" => KeyCode::F5,
+        "F6" => KeyCode::F6,
+        "F7" => KeyCode::F7,
+        "F8" => KeyCode::F8,
+        "F9" => KeyCode::F9,
+        "F10" => KeyCode::F10,
+        "F11" => KeyCode::F11,
+        "F12" => KeyCode::F12,
+        "Up" | "ArrowUp" => KeyCode::ArrowUp,
+        "Down" | "ArrowDown" => KeyCode::ArrowDown,
+        "Left" | "ArrowLeft" => KeyCode::ArrowLeft,
+        "Right" | "ArrowRight" => KeyCode::ArrowRight,
+        "Enter" | "Return" => KeyCode::Enter,
+        "Esc" | "Escape" => KeyCode::Escape,
+        "Tab" => KeyCode::Tab,
+        "Space" => KeyCode::Space,
+        "Backspace" => KeyCode::Backspace,
+        "Delete" => KeyCode::Delete,
+        "Insert" => KeyCode::Insert,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        "-" | "Minus" => KeyCode::Minus,
+        "=" | "Equal" => KeyCode::Equal,
+        "[" | "BracketLeft" => KeyCode::BracketLeft,
+        "]" | "BracketRight" => KeyCode::BracketRight,
+        "," | "Comma" => KeyCode::Comma,
+        "." | "Period" => KeyCode::Period,
+        "/" | "Slash" => KeyCode::Slash,
+        "\\" | "Backslash" => KeyCode::Backslash,
+        ";" | "Semicolon" => KeyCode::Semicolon,
+        "'" | "Quote" => KeyCode::Quote,
+        "`" | "Backquote" => KeyCode::Backquote,
+        "ShiftLeft" => KeyCode::ShiftLeft,
+        "ShiftRight" => KeyCode::ShiftRight,
+        "ControlLeft" | "CtrlLeft" => KeyCode::ControlLeft,
+        "ControlRight" | "CtrlRight" => KeyCode::ControlRight,
+        "AltLeft" => KeyCode::AltLeft,
+        "AltRight" => KeyCode::AltRight,
+        _ => return None,
+    };
+
+    Some(Trigger::Key(key))
+}
+
+//====================================================================
+
+#[derive(Unique)]
+pub struct Keymap {
+    bindings: HashMap<Action, Vec<Chord>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use Action as A;
+        use KeyCode as K;
+
+        let keys = |keys: &[KeyCode]| keys.iter().map(|key| Chord::key(*key)).collect();
+        let mouse = |button| vec![Chord::mouse(button)];
+
+        // Also bound with the modifiers that change how they behave
+        let fast = |key| vec![Chord::key(key), Chord::key(key).with_shift()];
+        let select = |button| {
+            let chord = Chord::mouse(button);
+            vec![
+                chord,
+                chord.with_ctrl(),
+                chord.with_shift(),
+                chord.with_ctrl().with_shift(),
+            ]
+        };
+
+        let bindings = HashMap::from([
+            (A::ScrollUp, fast(K::KeyW)),
+            (A::ScrollDown, fast(K::KeyS)),
+            (A::ZoomIn, fast(K::KeyR)),
+            (A::ZoomOut, fast(K::KeyF)),
+            (A::FastModifier, keys(&[K::ShiftLeft])),
+            (A::WheelZoomModifier, keys(&[K::ControlLeft])),
+            //
+            (A::FocusLeft, keys(&[K::ArrowLeft, K::KeyH])),
+            (A::FocusRight, keys(&[K::ArrowRight, K::KeyL])),
+            (A::FocusUp, keys(&[K::ArrowUp, K::KeyK])),
+            (A::FocusDown, keys(&[K::ArrowDown, K::KeyJ])),
+            (A::FocusNext, keys(&[K::KeyN])),
+            (A::FocusPrev, keys(&[K::KeyP])),
+            (A::OpenFocused, keys(&[K::Enter])),
+            (A::ToggleFullscreen, keys(&[K::Tab])),
+            (A::CycleGrouping, keys(&[K::KeyG])),
+            (A::ToggleCaptions, keys(&[K::KeyC])),
+            (A::CycleBackground, keys(&[K::KeyB])),
+            (A::ToggleTransparencyChecker, keys(&[K::KeyV])),
+            (A::ToggleAnimateGifs, keys(&[K::KeyA])),
+            //
+            (A::Select, select(MouseButton::Left)),
+            (
+                A::ToggleSelectModifier,
+                keys(&[K::ControlLeft, K::ControlRight]),
+            ),
+            (A::RangeSelectModifier, keys(&[K::ShiftLeft, K::ShiftRight])),
+            (A::Deselect, keys(&[K::Escape])),
+            (A::CloseViewer, mouse(MouseButton::Right)),
+            //
+            (A::ViewerActualSize, keys(&[K::Digit1])),
+            (A::ViewerToggleFill, keys(&[K::Digit0])),
+            (A::ViewerPan, mouse(MouseButton::Left)),
+            //
+            // Shares space with the slideshow, which takes it while running
+            (A::GifPlayPause, keys(&[K::Space])),
+            (A::GifStepForward, keys(&[K::Period])),
+            (A::GifStepBack, keys(&[K::Comma])),
+            (A::GifFaster, keys(&[K::Equal])),
+            (A::GifSlower, keys(&[K::Minus])),
+            //
+            (A::ExposureUp, keys(&[K::KeyE])),
+            (A::ExposureDown, keys(&[K::KeyQ])),
+            (A::ExposureReset, keys(&[K::Backquote])),
+            (A::CycleToneMap, keys(&[K::KeyT])),
+            //
+            (A::BrightnessDown, keys(&[K::Digit3])),
+            (A::BrightnessUp, keys(&[K::Digit4])),
+            (A::ContrastDown, keys(&[K::Digit5])),
+            (A::ContrastUp, keys(&[K::Digit6])),
+            (A::GammaDown, keys(&[K::Digit7])),
+            (A::GammaUp, keys(&[K::Digit8])),
+            (A::InvertColors, keys(&[K::KeyI])),
+            (A::CycleChannel, keys(&[K::KeyM])),
+            (A::ResetAdjustments, keys(&[K::Backspace])),
+            //
+            (A::SlideshowToggle, keys(&[K::F5])),
+            (A::SlideshowPause, keys(&[K::Space])),
+            (A::SlideshowShuffle, keys(&[K::F6])),
+            (A::SlideshowLoop, keys(&[K::F7])),
+            (A::SlideshowCrossfade, keys(&[K::F8])),
+            (A::SlideshowFaster, keys(&[K::BracketLeft])),
+            (A::SlideshowSlower, keys(&[K::BracketRight])),
+        ]);
+
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(crate::NAME).join("keymap.toml"))
+    }
+
+    /// Apply a keymap file on top of the current bindings
+    fn apply(&mut self, file: KeymapFile) {
+        let parse = |action: &Action, chords: Vec<String>| {
+            chords
+                .into_iter()
+                .filter_map(|chord| match Chord::parse(&chord) {
+                    Some(parsed) => Some(parsed),
+                    None => {
+                        log::warn!("Unknown key chord '{}' bound to {:?}", chord, action);
+                        None
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+
+        file.bind.into_iter().for_each(|(action, chords)| {
+            let chords = parse(&action, chords);
+            self.bindings.insert(action, chords);
+        });
+
+        file.add.into_iter().for_each(|(action, chords)| {
+            let chords = parse(&action, chords);
+            self.bindings.entry(action).or_default().extend(chords);
+        });
+    }
+
+    fn chords(&self, action: Action) -> impl Iterator<Item = &Chord> {
+        self.bindings.get(&action).into_iter().flatten()
+    }
+}
+
+/// `bind` replaces all chords for an action while `add` keeps the defaults
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct KeymapFile {
+    bind: HashMap<Action, Vec<String>>,
+    add: HashMap<Action, Vec<String>>,
+}
+
+//====================================================================
+
+#[derive(Borrow, BorrowInfo)]
+pub struct ActionInput<'v> {
+    keymap: UniqueView<'v, Keymap>,
+    keys: UniqueView<'v, Input<KeyCode>>,
+    mouse: UniqueView<'v, Input<MouseButton>>,
+}
+
+impl ActionInput<'_> {
+    fn check(&self, action: Action, check: impl Fn(Trigger) -> bool) -> bool {
+        self.keymap
+            .chords(action)
+            .any(|chord| chord.modifiers_held(&self.keys) && check(chord.trigger))
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.check(action, |trigger| match trigger {
+            Trigger::Key(key) => self.keys.pressed(key),
+            Trigger::Mouse(button) => self.mouse.pressed(button),
+        })
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.check(action, |trigger| match trigger {
+            Trigger::Key(key) => self.keys.just_pressed(key),
+            Trigger::Mouse(button) => self.mouse.just_pressed(button),
+        })
+    }
+}
+
+//====================================================================
+
+fn sys_setup_keymap(all_storages: AllStoragesView) {
+    let mut keymap = Keymap::default();
+
+    match Keymap::path() {
+        Some(path) if path.exists() => match std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| toml::from_str::<KeymapFile>(&data).map_err(|e| e.to_string()))
+        {
+            Ok(file) => {
+                log::info!("Loaded keymap from {:?}", path);
+                keymap.apply(file);
+            }
+            Err(e) => log::warn!("Failed to load keymap from {:?}: {}", path, e),
+        },
+        _ => log::trace!("No keymap file found, using default bindings"),
+    }
+
+    all_storages.add_unique(keymap);
+}
+
+//====================================================================
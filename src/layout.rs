@@ -1,31 +1,31 @@
 //====================================================================
 
+use std::{ops::Range, time::Duration};
+
 use cabat::{
     common::{WindowResizeEvent, WindowSize},
-    renderer::{
-        text::{Metrics, Text2dBuffer, TextFontSystem},
-        Device, Queue,
-    },
-    runner::tools::{Input, KeyCode, MouseButton, MouseInput, Time},
+    renderer::text::{Metrics, Text2dBuffer, TextFontSystem},
+    runner::tools::{MouseInput, Time},
     shipyard_tools::{prelude::*, UniqueTools},
 };
 use shipyard::{
-    AllStoragesView, EntitiesView, EntityId, Get, IntoIter, IntoWithId, IntoWorkload, Remove,
-    Unique, View, ViewMut,
+    AllStoragesView, Component, EntitiesView, EntityId, Get, IntoIter, IntoWithId, IntoWorkload,
+    Remove, Unique, View, ViewMut,
 };
 
 use crate::{
+    captions::ImageCaption,
+    config::{NavigationSettings, Settings, SettingsChangedEvent},
     images::{
-        Color, GifImage, GifTimer, ImageCreator, ImageDirtier, ImageDirty, ImageHovered,
-        ImageIndex, ImageMeta, ImageSelected, ImageShown, ImageSize, Pos, StandardImage, ToRemove,
-    },
-    renderer::{
-        camera::MainCamera,
-        gif2d_pipeline::{Gif2dInstance, Gif2dInstanceRaw, Gif2dPipeline},
-        texture2d_pipeline::{Texture2dInstance, Texture2dInstanceRaw, Texture2dPipeline},
+        GifImage, Image, ImageCreator, ImageDirtier, ImageDirty, ImageFade, ImageFocused,
+        ImageHovered, ImageIndex, ImageMeta, ImageSelected, ImageShown, ImageSize, ImageVisible,
+        Pos, StandardImage, ToRemove,
     },
+    keybinds::{Action, ActionInput},
+    renderer::camera::{MainCamera, UiCamera},
     storage::Storage,
     tools::aabb_point,
+    viewer::Viewer,
 };
 
 //====================================================================
@@ -40,16 +40,15 @@ impl Plugin for LayoutPlugin {
                 Stages::Update,
                 (
                     (sys_navigate_layout, sys_hover_images).into_sequential_workload(),
-                    sys_select_images,
+                    sys_focus_images,
+                    sys_toggle_fullscreen,
                 ),
             )
             .add_workload_post(
                 Stages::Update,
                 (
+                    sys_set_visibility,
                     sys_order_images,
-                    sys_rebuild_images,
-                    sys_tick_gifs,
-                    sys_rebuild_gifs,
                     sys_reposition_text_dirty,
                     // sys_debug_layout,
                 )
@@ -67,7 +66,18 @@ impl Plugin for LayoutPlugin {
                 )
                     .into_workload(),
             )
-            .add_event::<ScrollEvent>((sys_reposition_text).into_workload());
+            .add_event::<ScrollEvent>((sys_reposition_text).into_workload())
+            .add_event::<SettingsChangedEvent>(
+                (sys_apply_settings, sys_resize_layout, sys_reposition_text)
+                    .into_sequential_workload(),
+            )
+            .add_event::<RelayoutEvent>(
+                (sys_resize_layout, sys_resize_selected, sys_reposition_text)
+                    .into_sequential_workload(),
+            )
+            .add_event::<FullscreenEvent>(
+                (sys_resize_selected, sys_reposition_text).into_workload(),
+            );
     }
 }
 
@@ -80,37 +90,458 @@ pub struct LayoutManager {
     width: f32,
     columns: u32,
     tile_size: glam::Vec2,
+    // Default tile size from the settings last applied
+    tile_size_setting: Option<[f32; 2]>,
     tile_spacing: glam::Vec2,
 
     max_tile_size: glam::Vec2,
     min_tile_size: glam::Vec2,
 
+    // Fraction of the window given to the grid while an image is selected
+    split: f32,
+    min_grid_width: f32,
+    min_pane_width: f32,
+
+    groups: Vec<Group>,
+    header_height: f32,
+
     selected: bool,
+    fullscreen: bool,
+    captions: bool,
 }
 
-impl Default for LayoutManager {
-    fn default() -> Self {
-        Self {
+impl LayoutManager {
+    pub fn new(settings: &Settings) -> Self {
+        let mut layout = Self {
             image_count: 0,
             width: 1.,
             columns: 1,
-            tile_size: glam::vec2(200., 200.),
-            tile_spacing: glam::vec2(10., 60.),
+            tile_size: glam::Vec2::ZERO,
+            tile_size_setting: None,
+            tile_spacing: glam::Vec2::ZERO,
 
-            max_tile_size: glam::vec2(500., 500.),
-            min_tile_size: glam::vec2(80., 80.),
+            max_tile_size: glam::Vec2::ZERO,
+            min_tile_size: glam::Vec2::ZERO,
+
+            split: 0.5,
+            min_grid_width: 240.,
+            min_pane_width: 240.,
+
+            groups: Vec::new(),
+            header_height: 48.,
 
             selected: false,
+            fullscreen: false,
+            captions: true,
+        };
+
+        layout.apply_settings(settings);
+        layout
+    }
+
+    fn apply_settings(&mut self, settings: &Settings) {
+        let layout = &settings.layout;
+
+        self.tile_spacing = glam::Vec2::from(layout.tile_spacing);
+        self.min_tile_size = glam::Vec2::from(layout.min_tile_size);
+        self.max_tile_size = glam::Vec2::from(layout.max_tile_size).max(self.min_tile_size);
+
+        // Only a changed default replaces the current zoom, which may have come
+        // from a folder's saved state
+        if self.tile_size_setting != Some(layout.tile_size) {
+            self.tile_size_setting = Some(layout.tile_size);
+            self.tile_size = glam::Vec2::from(layout.tile_size);
         }
+        self.tile_size = self.tile_size.clamp(self.min_tile_size, self.max_tile_size);
+
+        self.split = layout.split_ratio.clamp(0.05, 0.95);
+        self.captions = settings.appearance.show_captions;
+    }
+
+    #[inline]
+    pub fn split(&self) -> f32 {
+        self.split
+    }
+
+    #[inline]
+    pub fn set_split(&mut self, split: f32) {
+        self.split = split.clamp(0.05, 0.95);
+    }
+
+    /// Width of the grid next to the selected pane, keeping both above their minimum widths
+    fn split_width(&self, window_width: f32) -> f32 {
+        let max = (window_width - self.min_pane_width).max(self.min_grid_width);
+        (window_width * self.split).clamp(self.min_grid_width, max)
+    }
+
+    /// Captions are hidden along with the grid
+    #[inline]
+    fn show_captions(&self) -> bool {
+        self.captions && !self.fullscreen
+    }
+
+    #[inline]
+    pub fn image_count(&self) -> u32 {
+        self.image_count
     }
-}
 
-impl LayoutManager {
     pub fn next(&mut self) -> u32 {
         let next = self.image_count;
         self.image_count += 1;
         next
     }
+
+    /// Start counting images again, for when a new folder replaces the old one
+    #[inline]
+    pub fn clear_images(&mut self) {
+        self.image_count = 0;
+    }
+
+    #[inline]
+    pub fn selected(&self) -> bool {
+        self.selected
+    }
+
+    /// Whether the selected image covers the whole window, hiding the grid
+    #[inline]
+    pub fn fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    /// Center and size of the selected image pane in screen space
+    pub fn viewer_pane(&self, window_size: &WindowSize) -> (glam::Vec2, glam::Vec2) {
+        if self.fullscreen {
+            return (
+                glam::Vec2::ZERO,
+                glam::vec2(window_size.width_f32(), window_size.height_f32()),
+            );
+        }
+
+        let width = window_size.width_f32() - self.width;
+        let height = window_size.height_f32();
+
+        (
+            glam::vec2(window_size.width_f32() / 2. - width / 2., 0.),
+            glam::vec2(width, height),
+        )
+    }
+
+    /// Index of the tile whose slot is closest to the given world position
+    pub fn nearest_index(&self, window_size: &WindowSize, point: glam::Vec2) -> u32 {
+        if self.image_count == 0 {
+            return 0;
+        }
+
+        let pitch = self.tile_pitch();
+        let start_x = self.grid_start_x(window_size);
+        let mut y = window_size.height_f32() / 2.;
+
+        for section in self.sections() {
+            y -= self.section_header_height(&section);
+
+            let height = self.section_rows(&section) as f32 * pitch.y;
+
+            if point.y >= y - height {
+                if section.len == 0 || section.collapsed {
+                    return section.start.min(self.image_count - 1);
+                }
+
+                let column =
+                    f32::round((point.x - start_x) / pitch.x).clamp(0., self.columns as f32 - 1.);
+                let row = f32::round((y - self.tile_size.y / 2. - point.y) / pitch.y)
+                    .clamp(0., self.section_rows(&section) as f32 - 1.);
+
+                return (section.start + row as u32 * self.columns + column as u32)
+                    .min(section.start + section.len - 1);
+            }
+
+            y -= height;
+        }
+
+        self.image_count - 1
+    }
+
+    /// Range the main camera's y translation can be scrolled within
+    pub fn scroll_bounds(&self) -> (f32, f32) {
+        let height = self
+            .sections()
+            .map(|section| self.section_height(&section))
+            .sum::<f32>();
+
+        let last_row = (height - self.tile_pitch().y).max(0.) * -1.;
+
+        (last_row, self.tile_size.y * 0.8)
+    }
+
+    /// Width of the grid area on the left of the window
+    #[inline]
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn tile_size(&self) -> glam::Vec2 {
+        self.tile_size
+    }
+
+    /// Resize tiles without changing the setting, for folders with their own zoom
+    pub fn set_tile_size(&mut self, tile_size: glam::Vec2) {
+        self.tile_size = tile_size.clamp(self.min_tile_size, self.max_tile_size);
+    }
+
+    #[inline]
+    pub fn tile_pitch(&self) -> glam::Vec2 {
+        self.tile_size + self.spacing()
+    }
+
+    /// Captions sit in the space between rows, which closes up while they're hidden
+    #[inline]
+    fn spacing(&self) -> glam::Vec2 {
+        match self.captions {
+            true => self.tile_spacing,
+            false => glam::vec2(self.tile_spacing.x, self.tile_spacing.x),
+        }
+    }
+
+    /// Font size for captions with the given number of lines, shrinking so they all fit
+    fn caption_font_size(&self, lines: usize) -> f32 {
+        let scale = (self.tile_size.x / self.max_tile_size.x) * 30. + 2.;
+        scale.min(self.tile_spacing.y / (lines.max(1) as f32 * 1.2))
+    }
+
+    /// Indexes of the tiles (and their captions) overlapping the given world space rows
+    pub fn visible_ranges(
+        &self,
+        window_size: &WindowSize,
+        top: f32,
+        bottom: f32,
+    ) -> Vec<Range<u32>> {
+        if self.fullscreen || self.image_count == 0 {
+            return Vec::new();
+        }
+
+        let pitch = self.tile_pitch();
+        let mut y = window_size.height_f32() / 2.;
+        let mut ranges = Vec::new();
+
+        for section in self.sections() {
+            y -= self.section_header_height(&section);
+
+            let rows = self.section_rows(&section);
+            let section_top = y;
+            y -= rows as f32 * pitch.y;
+
+            if rows == 0 || section_top < bottom || y > top {
+                continue;
+            }
+
+            let first_row = f32::floor((section_top - top) / pitch.y).max(0.) as u32;
+            let last_row =
+                (f32::floor((section_top - bottom) / pitch.y).max(0.) as u32).min(rows - 1);
+
+            let end = section.start + section.len;
+
+            ranges.push(
+                (section.start + first_row * self.columns).min(end)
+                    ..(section.start + (last_row + 1) * self.columns).min(end),
+            );
+        }
+
+        ranges
+    }
+
+    /// World position of the center of the tile at the given index
+    pub fn tile_pos(&self, window_size: &WindowSize, index: u32) -> glam::Vec2 {
+        let pitch = self.tile_pitch();
+        let start_x = self.grid_start_x(window_size);
+
+        let mut y = window_size.height_f32() / 2.;
+
+        for section in self.sections() {
+            y -= self.section_header_height(&section);
+
+            if index >= section.start && index < section.start + section.len {
+                let local = index - section.start;
+
+                return glam::vec2(
+                    start_x + (local % self.columns) as f32 * pitch.x,
+                    y - self.tile_size.y / 2. - (local / self.columns) as f32 * pitch.y,
+                );
+            }
+
+            y -= self.section_rows(&section) as f32 * pitch.y;
+        }
+
+        glam::vec2(start_x, y - self.tile_size.y / 2.)
+    }
+
+    /// Tile reached by moving across the grid and up or down its rows from the
+    /// given index. Rows are as shown, so moving between groups keeps to the same
+    /// column, and tiles in collapsed groups are skipped.
+    pub fn step_index(&self, index: u32, across: i32, down: i32) -> Option<u32> {
+        let rows = self.visible_rows();
+        let last_row = rows.len().checked_sub(1)?;
+
+        // Tiles in a collapsed group start from the row after it
+        let mut row = rows
+            .iter()
+            .position(|row| row.contains(&index))
+            .or_else(|| rows.iter().position(|row| row.start > index))
+            .unwrap_or(last_row);
+
+        let row_len = |row: usize| rows[row].end - rows[row].start;
+        let mut column = index.saturating_sub(rows[row].start).min(row_len(row) - 1);
+
+        // Moving across wraps onto the next or previous row
+        for _ in 0..across.unsigned_abs() {
+            match across > 0 {
+                true if column + 1 < row_len(row) => column += 1,
+                true if row < last_row => (row, column) = (row + 1, 0),
+                false if column > 0 => column -= 1,
+                false if row > 0 => (row, column) = (row - 1, row_len(row - 1) - 1),
+                _ => break,
+            }
+        }
+
+        let row = (row as i64 + down as i64).clamp(0, last_row as i64) as usize;
+        Some(rows[row].start + column.min(row_len(row) - 1))
+    }
+
+    /// Indexes of the tiles on each row of the grid, top to bottom
+    fn visible_rows(&self) -> Vec<Range<u32>> {
+        let columns = self.columns;
+
+        self.sections()
+            .flat_map(|section| {
+                let end = section.start + section.len;
+
+                (0..self.section_rows(&section)).map(move |row| {
+                    let start = section.start + row * columns;
+                    start..(start + columns).min(end)
+                })
+            })
+            .collect()
+    }
+
+    /// World position of the top left corner of each group header
+    pub fn header_positions(&self, window_size: &WindowSize) -> Vec<glam::Vec2> {
+        let pitch = self.tile_pitch();
+        let x = self.grid_start_x(window_size) - self.tile_size.x / 2.;
+
+        let mut y = window_size.height_f32() / 2.;
+
+        self.sections()
+            .filter_map(|section| {
+                let header = match section.header {
+                    true => Some(glam::vec2(x, y)),
+                    false => None,
+                };
+
+                y -= self.section_height(&section);
+                header
+            })
+            .collect()
+    }
+
+    /// Index of the group whose header is under the given world position
+    pub fn header_at(&self, window_size: &WindowSize, point: glam::Vec2) -> Option<usize> {
+        if self.fullscreen || point.x > -window_size.width_f32() / 2. + self.width {
+            return None;
+        }
+
+        self.header_positions(window_size)
+            .into_iter()
+            .position(|pos| point.y <= pos.y && point.y > pos.y - self.header_height)
+    }
+
+    #[inline]
+    pub fn groups(&self) -> &[Group] {
+        &self.groups
+    }
+
+    #[inline]
+    pub fn set_groups(&mut self, groups: Vec<Group>) {
+        self.groups = groups;
+    }
+
+    pub fn toggle_group(&mut self, group: usize) {
+        if let Some(group) = self.groups.get_mut(group) {
+            group.collapsed = !group.collapsed;
+        }
+    }
+
+    // Grid is centered within the area left of the selected pane
+    fn grid_start_x(&self, window_size: &WindowSize) -> f32 {
+        let pitch = self.tile_pitch();
+
+        let offset_x = -window_size.width_f32() / 2. + self.width / 2.;
+        let row_width = self.columns as f32 * pitch.x;
+
+        pitch.x / 2. + offset_x - row_width / 2.
+    }
+
+    /// Without any groups, every image is laid out in a single section with no header
+    fn sections(&self) -> impl Iterator<Item = Section> + '_ {
+        let ungrouped = self.groups.is_empty().then(|| Section {
+            start: 0,
+            len: self.image_count,
+            collapsed: false,
+            header: false,
+        });
+
+        ungrouped
+            .into_iter()
+            .chain(self.groups.iter().map(|group| Section {
+                start: group.start,
+                len: group.len,
+                collapsed: group.collapsed,
+                header: true,
+            }))
+    }
+
+    #[inline]
+    fn section_rows(&self, section: &Section) -> u32 {
+        match section.collapsed {
+            true => 0,
+            false => section.len.div_ceil(self.columns),
+        }
+    }
+
+    #[inline]
+    fn section_header_height(&self, section: &Section) -> f32 {
+        match section.header {
+            true => self.header_height,
+            false => 0.,
+        }
+    }
+
+    #[inline]
+    fn section_height(&self, section: &Section) -> f32 {
+        self.section_header_height(section)
+            + self.section_rows(section) as f32 * self.tile_pitch().y
+    }
+}
+
+/// Images sharing a folder or month, shown under a header and starting on a new row
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub title: String,
+    pub start: u32,
+    pub len: u32,
+    pub collapsed: bool,
+}
+
+/// Title text shown above a group of tiles
+#[derive(Component)]
+pub struct GroupHeader {
+    pub group: usize,
+}
+
+struct Section {
+    start: u32,
+    len: u32,
+    collapsed: bool,
+    header: bool,
 }
 
 #[derive(Unique)]
@@ -120,36 +551,96 @@ pub struct LayoutNavigation {
     move_mod: f32,
     zoom_speed: f32,
     zoom_mod: f32,
+
+    // How quickly the camera eases towards the scroll target
+    scroll_smoothing: f32,
+    // Velocity added per unit of mouse wheel movement
+    wheel_impulse: f32,
+    // How quickly wheel momentum decays
+    wheel_friction: f32,
 }
 
-impl Default for LayoutNavigation {
-    fn default() -> Self {
+impl LayoutNavigation {
+    fn new(settings: &NavigationSettings) -> Self {
         Self {
-            scroll_mod: 4.,
-            move_speed: 800.,
-            move_mod: 3.,
-            zoom_speed: 120.,
-            zoom_mod: 2.1,
+            scroll_mod: settings.scroll_mod,
+            move_speed: settings.move_speed,
+            move_mod: settings.move_mod,
+            zoom_speed: settings.zoom_speed,
+            zoom_mod: settings.zoom_mod,
+
+            scroll_smoothing: settings.scroll_smoothing,
+            wheel_impulse: settings.wheel_impulse,
+            wheel_friction: settings.wheel_friction,
         }
     }
 }
 
+#[derive(Unique, Default)]
+pub struct ScrollController {
+    target: f32,
+    velocity: f32,
+    // Skip easing for the next frame
+    snap: bool,
+}
+
+impl ScrollController {
+    /// Animate the camera towards the given y position
+    #[inline]
+    pub fn scroll_to(&mut self, y: f32) {
+        self.target = y;
+        self.velocity = 0.;
+    }
+
+    /// Move the camera straight to the given y position
+    #[inline]
+    pub fn jump_to(&mut self, y: f32) {
+        self.scroll_to(y);
+        self.snap = true;
+    }
+
+    #[inline]
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+}
+
 //====================================================================
 
 #[derive(Event)]
-struct SelectedEvent {
-    selected: Option<EntityId>,
+pub(crate) struct SelectedEvent {
+    pub selected: Option<EntityId>,
+    // Crossfade from the previously shown image over this duration
+    pub fade: Option<Duration>,
 }
 
 #[derive(Event)]
 struct ScrollEvent;
 
+#[derive(Event)]
+struct FullscreenEvent;
+
+/// The grid's shape changed without the window resizing, like moving the splitter
+/// or collapsing a group
+#[derive(Event)]
+pub(crate) struct RelayoutEvent;
+
 //====================================================================
 
-fn sys_setup_layout(all_storages: AllStoragesView) {
+fn sys_setup_layout(all_storages: AllStoragesView, settings: Res<Settings>) {
     all_storages
-        .insert(LayoutManager::default())
-        .insert(LayoutNavigation::default());
+        .insert(LayoutManager::new(&settings))
+        .insert(LayoutNavigation::new(&settings.navigation))
+        .insert(ScrollController::default());
+}
+
+fn sys_apply_settings(
+    settings: Res<Settings>,
+    mut layout: ResMut<LayoutManager>,
+    mut navigation: ResMut<LayoutNavigation>,
+) {
+    layout.apply_settings(&settings);
+    *navigation = LayoutNavigation::new(&settings.navigation);
 }
 
 fn sys_resize_layout(
@@ -160,12 +651,11 @@ fn sys_resize_layout(
     mut camera: ResMut<MainCamera>,
 ) {
     layout.width = match layout.selected {
-        true => size.width_f32() / 2.,
+        true => layout.split_width(size.width_f32()),
         false => size.width_f32(),
     };
 
-    layout.columns =
-        (layout.width as u32 / (layout.tile_size.x + layout.tile_spacing.x) as u32).max(1);
+    layout.columns = (layout.width as u32 / layout.tile_pitch().x as u32).max(1);
 
     image_dirtier.mark_all_dirty();
 
@@ -184,41 +674,36 @@ fn sys_resize_layout(
 
 fn sys_order_images(
     layout: Res<LayoutManager>,
-    size: Res<WindowSize>,
+    window_size: Res<WindowSize>,
 
     mut vm_pos: ViewMut<Pos>,
     mut vm_size: ViewMut<ImageSize>,
     v_index: View<ImageIndex>,
     v_meta: View<ImageMeta>,
+    v_header: View<GroupHeader>,
     v_dirty: View<ImageDirty>,
 ) {
     if v_dirty.is_empty() {
         return;
     }
 
-    let offset_x = match layout.selected {
-        true => -layout.width / 2.,
-        false => 0.,
-    };
-
-    let row_width = layout.columns as f32 * (layout.tile_size.x + layout.tile_spacing.x);
+    // Headers are placed by their top left corner
+    let header_positions = layout.header_positions(&window_size);
 
-    let start_x = (layout.tile_size.x + layout.tile_spacing.x) / 2. + offset_x + -row_width / 2.;
-    let start_y = size.height_f32() / 2. - layout.tile_size.y / 2.;
+    (&mut vm_pos, &v_header).iter().for_each(|(pos, header)| {
+        if let Some(header_pos) = header_positions.get(header.group) {
+            pos.x = header_pos.x;
+            pos.y = header_pos.y;
+        }
+    });
 
     (&mut vm_pos, &mut vm_size, &v_index, &v_meta, &v_dirty)
         .iter()
         .for_each(|(pos, size, index, meta, _)| {
-            let x = start_x
-                + (index.index % layout.columns) as f32
-                    * (layout.tile_size.x + layout.tile_spacing.x);
-
-            let y = start_y
-                - (index.index / layout.columns) as f32
-                    * (layout.tile_size.y + layout.tile_spacing.y);
+            let tile_pos = layout.tile_pos(&window_size, index.index);
 
-            pos.x = x;
-            pos.y = y;
+            pos.x = tile_pos.x;
+            pos.y = tile_pos.y;
 
             let wratio = layout.tile_size.x / meta.texture_resolution.width as f32;
             let hratio = layout.tile_size.y / meta.texture_resolution.height as f32;
@@ -229,91 +714,6 @@ fn sys_order_images(
         });
 }
 
-fn sys_rebuild_images(
-    queue: Res<Queue>,
-
-    v_pos: View<Pos>,
-    v_size: View<ImageSize>,
-    v_color: View<Color>,
-    v_image: View<StandardImage>,
-    v_dirty: View<ImageDirty>,
-) {
-    if v_dirty.is_empty() {
-        return;
-    }
-
-    (&v_pos, &v_size, &v_color, &v_image, &v_dirty)
-        .iter()
-        .for_each(|(pos, size, color, image, _)| {
-            image.instance.update(
-                queue.inner(),
-                Texture2dInstanceRaw {
-                    pos: pos.to_array(),
-                    size: size.to_array(),
-                    color: color.to_array(),
-                },
-            )
-        });
-}
-
-fn sys_rebuild_gifs(
-    queue: Res<Queue>,
-
-    v_pos: View<Pos>,
-    v_size: View<ImageSize>,
-    v_color: View<Color>,
-    v_gif: View<GifImage>,
-    v_dirty: View<ImageDirty>,
-) {
-    if v_dirty.is_empty() {
-        return;
-    }
-
-    (&v_pos, &v_size, &v_color, &v_gif, &v_dirty)
-        .iter()
-        .for_each(|(pos, size, color, gif, _)| {
-            gif.instance.update(
-                queue.inner(),
-                Gif2dInstanceRaw {
-                    pos: pos.to_array(),
-                    size: size.to_array(),
-                    color: color.to_array(),
-                    frame_x: (gif.frame % gif.frames_per_row) as f32,
-                    frame_y: (gif.frame / gif.frames_per_row) as f32,
-                    ..Default::default()
-                },
-            )
-        });
-}
-
-fn sys_tick_gifs(
-    entities: EntitiesView,
-    time: Res<Time>,
-    mut vm_gif: ViewMut<GifImage>,
-    mut vm_gif_timer: ViewMut<GifTimer>,
-    mut vm_dirty: ViewMut<ImageDirty>,
-) {
-    (&mut vm_gif, &mut vm_gif_timer)
-        .iter()
-        .with_id()
-        .for_each(|(id, (gif, timer))| {
-            timer.acc += *time.delta();
-
-            // let delay = timer.delay[gif.frame as usize];
-            let delay = timer.delay.get_delay(&gif.frame);
-
-            if timer.acc > delay {
-                timer.acc = std::time::Duration::ZERO;
-                gif.frame = gif.frame + 1;
-                if gif.frame >= gif.total_frames {
-                    gif.frame = 0;
-                }
-
-                entities.add_component(id, &mut vm_dirty, ImageDirty);
-            }
-        });
-}
-
 fn sys_reposition_text_dirty(
     layout: Res<LayoutManager>,
     size: Res<WindowSize>,
@@ -322,6 +722,9 @@ fn sys_reposition_text_dirty(
 
     v_pos: View<Pos>,
     v_index: View<ImageIndex>,
+    v_visible: View<ImageVisible>,
+    v_header: View<GroupHeader>,
+    mut vm_caption: ViewMut<ImageCaption>,
     mut vm_text: ViewMut<Text2dBuffer>,
     v_dirty: View<ImageDirty>,
 ) {
@@ -329,6 +732,21 @@ fn sys_reposition_text_dirty(
         return;
     }
 
+    reposition_headers(
+        &layout,
+        &size,
+        &camera,
+        &mut font_system,
+        &v_pos,
+        &v_header,
+        &mut vm_text,
+    );
+
+    // Newly loaded images start off screen until the visibility pass picks them up
+    (&v_index, &mut vm_text, &v_dirty, !&v_visible)
+        .iter()
+        .for_each(|(_, mut text, _, _)| hide_text(&mut text));
+
     let top = 0;
     let bottom = size.height() as i32;
     let left = 0;
@@ -337,11 +755,14 @@ fn sys_reposition_text_dirty(
     let start_x = camera.raw.translation.x + size.width_f32() / 2. - layout.tile_size.x / 2.;
     let start_y = camera.raw.translation.y + size.height_f32() / 2. + layout.tile_size.y / 2.;
 
-    let font_scale = (layout.tile_size.x / layout.max_tile_size.x) * 30. + 2.;
-
-    (&v_pos, &v_index, &mut vm_text, &v_dirty)
+    (&v_pos, &mut vm_caption, &mut vm_text, &v_dirty, &v_visible)
         .iter()
-        .for_each(|(pos, _, text, _)| {
+        .for_each(|(pos, mut caption, mut text, _, _)| {
+            if !layout.show_captions() {
+                hide_text(&mut text);
+                return;
+            }
+
             text.pos.0 = start_x + pos.x;
             text.pos.1 = start_y - pos.y;
 
@@ -350,12 +771,16 @@ fn sys_reposition_text_dirty(
             text.bounds.left = left;
             text.bounds.right = right;
 
+            let font_size = layout.caption_font_size(caption.line_count());
+
             text.set_metrics_and_size(
                 font_system.inner_mut(),
-                Metrics::relative(font_scale, 1.2),
+                Metrics::relative(font_size, 1.2),
                 Some(layout.tile_size.x),
                 Some(layout.tile_spacing.y),
             );
+
+            caption.fit(&mut text, &mut font_system, layout.tile_size.x, font_size);
         });
 }
 
@@ -366,9 +791,21 @@ fn sys_reposition_text(
     mut font_system: ResMut<TextFontSystem>,
 
     v_pos: View<Pos>,
-    v_index: View<ImageIndex>,
+    v_visible: View<ImageVisible>,
+    v_header: View<GroupHeader>,
+    mut vm_caption: ViewMut<ImageCaption>,
     mut vm_text: ViewMut<Text2dBuffer>,
 ) {
+    reposition_headers(
+        &layout,
+        &size,
+        &camera,
+        &mut font_system,
+        &v_pos,
+        &v_header,
+        &mut vm_text,
+    );
+
     let top = 0;
     let bottom = size.height() as i32;
     let left = 0;
@@ -377,11 +814,14 @@ fn sys_reposition_text(
     let start_x = camera.raw.translation.x + size.width_f32() / 2. - layout.tile_size.x / 2.;
     let start_y = camera.raw.translation.y + size.height_f32() / 2. + layout.tile_size.y / 2.;
 
-    let font_scale = (layout.tile_size.x / layout.max_tile_size.x) * 30. + 2.;
-
-    (&v_pos, &v_index, &mut vm_text)
+    (&v_pos, &mut vm_caption, &mut vm_text, &v_visible)
         .iter()
-        .for_each(|(pos, _, text)| {
+        .for_each(|(pos, mut caption, mut text, _)| {
+            if !layout.show_captions() {
+                hide_text(&mut text);
+                return;
+            }
+
             text.pos.0 = start_x + pos.x;
             text.pos.1 = start_y - pos.y;
 
@@ -390,28 +830,131 @@ fn sys_reposition_text(
             text.bounds.left = left;
             text.bounds.right = right;
 
+            let font_size = layout.caption_font_size(caption.line_count());
+
             text.set_metrics_and_size(
                 font_system.inner_mut(),
-                Metrics::relative(font_scale, 1.2),
+                Metrics::relative(font_size, 1.2),
                 Some(layout.tile_size.x),
                 Some(layout.tile_spacing.y),
             );
+
+            caption.fit(&mut text, &mut font_system, layout.tile_size.x, font_size);
+        });
+}
+
+fn reposition_headers(
+    layout: &LayoutManager,
+    size: &WindowSize,
+    camera: &MainCamera,
+    font_system: &mut TextFontSystem,
+
+    v_pos: &View<Pos>,
+    v_header: &View<GroupHeader>,
+    vm_text: &mut ViewMut<Text2dBuffer>,
+) {
+    let start_x = camera.raw.translation.x + size.width_f32() / 2.;
+    let start_y = camera.raw.translation.y + size.height_f32() / 2.;
+
+    // Sit the title towards the bottom of the header, just above its tiles
+    let padding = glam::vec2(12., layout.header_height * 0.3);
+
+    (v_pos, v_header, vm_text)
+        .iter()
+        .for_each(|(pos, header, mut text)| {
+            // Headers of removed groups are cleaned up at the end of the frame
+            if layout.fullscreen || header.group >= layout.groups.len() {
+                hide_text(&mut text);
+                return;
+            }
+
+            text.pos.0 = start_x + pos.x + padding.x;
+            text.pos.1 = start_y - pos.y + padding.y;
+
+            // Keep titles out of the selected pane
+            text.bounds.top = 0;
+            text.bounds.bottom = size.height() as i32;
+            text.bounds.left = 0;
+            text.bounds.right = layout.width as i32;
+
+            text.set_metrics_and_size(
+                font_system.inner_mut(),
+                Metrics::relative(20., 1.2),
+                Some(layout.width - padding.x * 2.),
+                Some(layout.header_height),
+            );
         });
 }
 
+// Zero sized bounds clip the whole buffer
+#[inline]
+fn hide_text(text: &mut Text2dBuffer) {
+    text.bounds.top = 0;
+    text.bounds.bottom = 0;
+    text.bounds.left = 0;
+    text.bounds.right = 0;
+}
+
 //====================================================================
 
-// TODO / OPTIMIZE - Only render text and images that are visible
-// fn sys_set_visiblity(
-//     layout: Res<LayoutManager>,
-//     camera: Res<Camera<MainCamera>>,
-//     v_index: View<ImageIndex>,
-//     vm_visible: ViewMut<ImageVisible>,
-// ) {
-//     let top = camera.raw.translation.y + camera.raw.top;
-//     let bottom = camera.raw.translation.y + camera.raw.bottom;
+fn sys_set_visibility(
+    layout: Res<LayoutManager>,
+    window_size: Res<WindowSize>,
+    camera: Res<MainCamera>,
 
-// }
+    entities: EntitiesView,
+    v_image: View<Image>,
+    v_index: View<ImageIndex>,
+    mut vm_visible: ViewMut<ImageVisible>,
+    mut vm_dirty: ViewMut<ImageDirty>,
+    mut vm_text: ViewMut<Text2dBuffer>,
+) {
+    let top = camera.raw.translation.y + camera.raw.top;
+    let bottom = camera.raw.translation.y + camera.raw.bottom;
+
+    let ranges = layout.visible_ranges(&window_size, top, bottom);
+    let in_view = |index: u32| ranges.iter().any(|range| range.contains(&index));
+
+    let hidden = (&v_index, &vm_visible)
+        .iter()
+        .with_id()
+        .filter(|(_, (index, _))| !in_view(index.index))
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+
+    // Captions aren't updated while hidden so clear them from the screen
+    hidden.into_iter().for_each(|id| {
+        vm_visible.remove(id);
+        entities.add_component(id, &mut vm_dirty, ImageDirty);
+
+        if let Ok(mut text) = (&mut vm_text).get(id) {
+            hide_text(&mut text);
+        }
+    });
+
+    let shown = (&v_index, !&vm_visible)
+        .iter()
+        .with_id()
+        .filter(|(_, (index, _))| in_view(index.index))
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+
+    shown.into_iter().for_each(|id| {
+        entities.add_component(id, &mut vm_visible, ImageVisible);
+        entities.add_component(id, &mut vm_dirty, ImageDirty);
+    });
+
+    // Images in the viewer aren't part of the grid and are always visible
+    let viewer = (&v_image, !&v_index, !&vm_visible)
+        .iter()
+        .with_id()
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+
+    viewer
+        .into_iter()
+        .for_each(|id| entities.add_component(id, &mut vm_visible, ImageVisible));
+}
 
 //====================================================================
 
@@ -462,13 +1005,19 @@ fn sys_navigate_layout(
     mut layout: ResMut<LayoutManager>,
     navigation: Res<LayoutNavigation>,
     mut camera: ResMut<MainCamera>,
+    ui_camera: Res<UiCamera>,
+    mut scroll: ResMut<ScrollController>,
 
-    keys: Res<Input<KeyCode>>,
+    actions: ActionInput,
     mouse: Res<MouseInput>,
     time: Res<Time>,
 
+    v_index: View<ImageIndex>,
+    v_hovered: View<ImageHovered>,
     mut image_dirtier: ImageDirtier,
 ) {
+    let delta = time.delta_seconds();
+
     // // DEBUG
     // let a = keys.pressed(KeyCode::KeyA);
     // let d = keys.pressed(KeyCode::KeyD);
@@ -476,39 +1025,61 @@ fn sys_navigate_layout(
     // camera.raw.translation.x += x;
 
     // Mods
-    let shift = keys.pressed(KeyCode::ShiftLeft);
-    let ctrl = keys.pressed(KeyCode::ControlLeft);
+    let shift = actions.pressed(Action::FastModifier);
+    let ctrl = actions.pressed(Action::WheelZoomModifier);
 
     // Move
-    let w = keys.pressed(KeyCode::KeyW) || keys.pressed(KeyCode::KeyK);
-    let s = keys.pressed(KeyCode::KeyS) || keys.pressed(KeyCode::KeyJ);
-    let mut y = (w as i8 - s as i8) as f32;
-    if !ctrl {
-        y += mouse.scroll().y * navigation.scroll_mod;
+    let w = actions.pressed(Action::ScrollUp);
+    let s = actions.pressed(Action::ScrollDown);
+    let y = (w as i8 - s as i8) as f32;
+
+    // The mouse wheel belongs to the viewer while hovering over it
+    let ui_mouse_pos = ui_camera.raw.screen_to_camera(mouse.screen_pos());
+    let (pane_pos, pane_size) = layout.viewer_pane(&window_size);
+    let over_viewer = layout.selected && aabb_point(ui_mouse_pos, pane_pos, pane_size);
+
+    let mut wheel = 0.;
+    if !ctrl && !over_viewer {
+        wheel = mouse.scroll().y;
     }
 
     // Zooming in and out
-    let r = keys.pressed(KeyCode::KeyR); // in
-    let f = keys.pressed(KeyCode::KeyF); // out
+    let r = actions.pressed(Action::ZoomIn);
+    let f = actions.pressed(Action::ZoomOut);
 
     let mut zoom = (r as i8 - f as i8) as f32;
-    if ctrl {
+    if ctrl && !over_viewer {
         zoom += mouse.scroll().y * navigation.scroll_mod;
     }
 
+    // Leave the grid where it is while it's hidden
+    let (y, zoom) = match layout.fullscreen {
+        true => (0., 0.),
+        false => (y, zoom),
+    };
+
     if zoom != 0. {
         let mut zoom_speed = zoom * navigation.zoom_speed;
         if shift {
             zoom_speed *= navigation.zoom_mod;
         }
 
-        // Store the current top row index
-        let cam_top = camera.raw.translation.y + camera.raw.top;
-        let top_row = f32::floor(cam_top / (layout.tile_size.y + layout.tile_spacing.y)) - 2.;
-        let top_row_start_index = layout.columns as f32 * top_row;
+        // Anchor on the tile under the cursor so it stays fixed on screen
+        let mouse_pos = camera.raw.screen_to_camera(mouse.screen_pos());
+
+        let anchor_index = match (&v_index, &v_hovered).iter().next() {
+            Some((index, _)) => index.index,
+            None => layout.nearest_index(&window_size, mouse_pos),
+        };
+
+        let anchor_pos = layout.tile_pos(&window_size, anchor_index);
+        let anchor_offset = (mouse_pos - anchor_pos) / layout.tile_pitch();
+        let screen_offset = mouse_pos.y - camera.raw.translation.y;
 
         //
 
+        // Only saved with the folder's state, the setting stays the default for
+        // folders that don't have one
         let speed = glam::vec2(zoom_speed, zoom_speed) * time.delta_seconds();
 
         layout.tile_size += speed;
@@ -520,47 +1091,67 @@ fn sys_navigate_layout(
 
         //
 
-        let prev_columns = layout.columns;
+        layout.columns = (layout.width as u32 / layout.tile_pitch().x as u32).max(1);
 
-        layout.columns =
-            (layout.width as u32 / (layout.tile_size.x + layout.tile_spacing.x) as u32).max(1);
+        let new_anchor_pos = layout.tile_pos(&window_size, anchor_index);
+        let new_mouse_y = new_anchor_pos.y + anchor_offset.y * layout.tile_pitch().y;
 
-        if prev_columns != layout.columns {
-            let start_y = window_size.height_f32() / 2. - layout.tile_size.y / 2.;
+        camera.raw.translation.y = new_mouse_y - screen_offset;
+        scroll.scroll_to(camera.raw.translation.y);
+    }
 
-            let sub = match prev_columns > layout.columns {
-                true => 1.,
-                false => 0.,
-            };
+    let mut speed = navigation.move_speed;
+    if shift {
+        speed *= navigation.move_mod;
+    }
 
-            let new_top_row = f32::ceil(top_row_start_index / layout.columns as f32) + sub;
-            let new_top_row_pos = new_top_row * (layout.tile_size.y + layout.tile_spacing.y);
+    // Keys move the target directly while the wheel adds momentum
+    if y != 0. {
+        scroll.target += y * delta * speed;
+    }
 
-            camera.raw.translation.y = start_y + new_top_row_pos - camera.raw.top;
+    if wheel != 0. {
+        let mut impulse = wheel * navigation.wheel_impulse;
+        if shift {
+            impulse *= navigation.move_mod;
         }
+
+        scroll.velocity += impulse;
     }
 
-    if y != 0. {
-        let delta = time.delta_seconds();
+    if scroll.velocity != 0. {
+        scroll.target += scroll.velocity * delta;
+        scroll.velocity *= f32::exp(-navigation.wheel_friction * delta);
 
-        let mut speed = navigation.move_speed;
-        if shift {
-            speed *= navigation.move_mod;
+        if scroll.velocity.abs() < 1. {
+            scroll.velocity = 0.;
         }
+    }
 
-        camera.raw.translation.y += y * delta * speed;
+    let (min_y, max_y) = layout.scroll_bounds();
+    let clamped = scroll.target.clamp(min_y, max_y);
 
-        let last_column = (layout.image_count / layout.columns) as f32
-            * (layout.tile_size.y + layout.tile_spacing.y)
-            * -1.;
+    if clamped != scroll.target {
+        scroll.target = clamped;
+        scroll.velocity = 0.;
+    }
 
-        let min_y = last_column;
-        let max_y = layout.tile_size.y * 0.8;
+    // Ease the camera towards the target
+    let diff = scroll.target - camera.raw.translation.y;
 
-        camera.raw.translation.y = camera.raw.translation.y.clamp(min_y, max_y);
+    if diff != 0. {
+        match scroll.snap || diff.abs() < 0.5 {
+            true => camera.raw.translation.y = scroll.target,
+            false => {
+                camera.raw.translation.y +=
+                    diff * (1. - f32::exp(-navigation.scroll_smoothing * delta))
+            }
+        }
 
         events.add_event(ScrollEvent);
     }
+
+    scroll.snap = false;
 }
 
 //====================================================================
@@ -571,112 +1162,228 @@ fn sys_hover_images(
     mouse: Res<MouseInput>,
 
     v_pos: View<Pos>,
-    mut vm_color: ViewMut<Color>,
     v_index: View<ImageIndex>,
+    v_visible: View<ImageVisible>,
 
     entities: EntitiesView,
-    mut vm_dirty: ViewMut<ImageDirty>,
     mut vm_hovered: ViewMut<ImageHovered>,
 ) {
     let mouse_pos = camera.raw.screen_to_camera(mouse.screen_pos());
 
+    // Nothing in the grid can be hovered while it's hidden
+    let hovering = |pos: &Pos| {
+        !layout.fullscreen && aabb_point(mouse_pos, glam::vec2(pos.x, pos.y), layout.tile_size)
+    };
+
     // Check already hovered images
     let to_remove = (&v_pos, &vm_hovered)
         .iter()
         .with_id()
-        .filter_map(|(id, (pos, _))| {
-            match aabb_point(mouse_pos, glam::vec2(pos.x, pos.y), layout.tile_size) {
+        .filter_map(
+            |(id, (pos, _))| match hovering(pos) && v_visible.contains(id) {
                 true => None,
                 false => Some(id),
-            }
-        })
+            },
+        )
         .collect::<Vec<_>>();
 
     to_remove.into_iter().for_each(|id| {
         vm_hovered.remove(id);
-        (&mut vm_color).get(id).unwrap().r = 1.;
-
-        entities.add_component(id, &mut vm_dirty, ImageDirty);
     });
 
-    // Find newly hovered images - use v_index to only select images part of grid
-    let image = (&v_pos, &v_index, !&vm_hovered)
+    // Find newly hovered images - use v_index to only select images part of grid.
+    // Tiles in collapsed groups are never visible.
+    let image = (&v_pos, &v_index, &v_visible, !&vm_hovered)
         .iter()
         .with_id()
-        .find(|(_, (pos, _, _))| aabb_point(mouse_pos, glam::vec2(pos.x, pos.y), layout.tile_size));
+        .find(|(_, (pos, _, _, _))| hovering(pos));
 
     let id = match image {
         Some((id, _)) => id,
         None => return,
     };
 
-    let mut color = (&mut vm_color).get(id).unwrap();
-    color.r = 0.;
-
-    entities.add_component(id, &mut vm_dirty, ImageDirty);
     entities.add_component(id, &mut vm_hovered, ImageHovered);
 }
 
-fn sys_select_images(
+fn sys_focus_images(
     mut events: ResMut<EventHandler>,
-    key_input: Res<Input<KeyCode>>,
-    mouse_input: Res<Input<MouseButton>>,
+    actions: ActionInput,
+
+    window_size: Res<WindowSize>,
+    layout: Res<LayoutManager>,
+    camera: Res<MainCamera>,
+    mut scroll: ResMut<ScrollController>,
 
     entities: EntitiesView,
-    v_hovered: View<ImageHovered>,
+    v_index: View<ImageIndex>,
     mut vm_selected: ViewMut<ImageSelected>,
+    mut vm_focused: ViewMut<ImageFocused>,
 ) {
-    match (
-        mouse_input.just_pressed(MouseButton::Left),
-        mouse_input.just_pressed(MouseButton::Right) | key_input.just_pressed(KeyCode::Escape),
-    ) {
-        (false, true) => {
-            events.add_event(SelectedEvent { selected: None });
-            return;
-        }
-        (false, false) => return,
-        _ => {}
+    if layout.image_count == 0 {
+        return;
+    }
+
+    let left = actions.just_pressed(Action::FocusLeft);
+    let right = actions.just_pressed(Action::FocusRight);
+    let up = actions.just_pressed(Action::FocusUp);
+    let down = actions.just_pressed(Action::FocusDown);
+
+    let next = actions.just_pressed(Action::FocusNext);
+    let prev = actions.just_pressed(Action::FocusPrev);
+    let enter = actions.just_pressed(Action::OpenFocused);
+
+    if !(left || right || up || down || next || prev || enter) {
+        return;
+    }
+
+    // Start from the focused image, falling back to the selected one
+    let current = (&v_index, &vm_focused)
+        .iter()
+        .map(|(index, _)| index.index)
+        .next()
+        .or_else(|| {
+            (&v_index, &vm_selected)
+                .iter()
+                .map(|(index, _)| index.index)
+                .next()
+        });
+
+    let across = (right || next) as i32 - (left || prev) as i32;
+    let rows = down as i32 - up as i32;
+
+    // Nothing focused yet, start from the first tile
+    let target = match current {
+        Some(current) => layout.step_index(current, across, rows),
+        None => layout.step_index(0, 0, 0),
+    };
+
+    let target = match target {
+        Some(target) => target,
+        None => return,
+    };
+
+    let id = match (&v_index)
+        .iter()
+        .with_id()
+        .find(|(_, index)| index.index == target)
+    {
+        Some((id, _)) => id,
+        None => return,
+    };
+
+    set_focused(&entities, &mut vm_focused, id);
+
+    // Scroll the camera so the focused tile (and its caption) stays in view
+    let pos = layout.tile_pos(&window_size, target);
+    let tile_top = pos.y + layout.tile_size.y / 2.;
+    let tile_bottom = pos.y - layout.tile_size.y / 2. - layout.spacing().y;
+
+    // Compare against the scroll target so repeated presses don't fight the animation
+    let cam_top = scroll.target() + camera.raw.top;
+    let cam_bottom = scroll.target() + camera.raw.bottom;
+
+    if tile_top > cam_top {
+        scroll.scroll_to(tile_top - camera.raw.top);
+    } else if tile_bottom < cam_bottom {
+        scroll.scroll_to(tile_bottom - camera.raw.bottom);
     }
 
-    if !mouse_input.just_pressed(MouseButton::Left) {
+    // Enter selects the focused image. While an image is already selected,
+    // stepping left or right moves straight to the previous/next image.
+    let select = enter || (layout.selected && (left || right || next || prev));
+
+    if select {
+        log::debug!("New image selected with id '{:?}'", id);
+
+        vm_selected.clear();
+        entities.add_component(id, &mut vm_selected, ImageSelected);
+
+        events.add_event(SelectedEvent {
+            selected: Some(id),
+            fade: None,
+        });
+    }
+}
+
+pub(crate) fn set_focused(
+    entities: &EntitiesView,
+    vm_focused: &mut ViewMut<ImageFocused>,
+    id: EntityId,
+) {
+    vm_focused.clear();
+    entities.add_component(id, &mut *vm_focused, ImageFocused);
+}
+
+fn sys_toggle_fullscreen(
+    mut events: ResMut<EventHandler>,
+    actions: ActionInput,
+    mut layout: ResMut<LayoutManager>,
+
+    entities: EntitiesView,
+    v_focused: View<ImageFocused>,
+    mut vm_selected: ViewMut<ImageSelected>,
+) {
+    if !actions.just_pressed(Action::ToggleFullscreen) {
         return;
     }
 
-    let hovered = v_hovered.iter().with_id().next();
+    if layout.selected {
+        layout.fullscreen = !layout.fullscreen;
+        events.add_event(FullscreenEvent);
+        return;
+    }
 
-    let id = match hovered {
+    // Nothing selected yet - open the focused image straight into fullscreen
+    let id = match v_focused.iter().with_id().next() {
         Some((id, _)) => id,
         None => return,
     };
 
-    log::debug!("New image selected with id '{:?}'", id);
+    layout.fullscreen = true;
 
-    // TODO - Set color of selected image (or not idk)
     vm_selected.clear();
     entities.add_component(id, &mut vm_selected, ImageSelected);
 
-    events.add_event(SelectedEvent { selected: Some(id) });
+    events.add_event(SelectedEvent {
+        selected: Some(id),
+        fade: None,
+    });
 }
 
 fn sys_process_selected(
     events: Res<EventHandler>,
-    device: Res<Device>,
-    texture_pipeline: Res<Texture2dPipeline>,
-    gif_pipeline: Res<Gif2dPipeline>,
     storage: Res<Storage>,
 
+    mut viewer: ResMut<Viewer>,
+
     mut image_creator: ImageCreator,
     mut vm_shown: ViewMut<ImageShown>,
+    mut vm_fade: ViewMut<ImageFade>,
 
     mut vm_remove: ViewMut<ToRemove>,
 ) {
     let event = events.get_event::<SelectedEvent>().unwrap();
 
-    // Remove all existing shown images
-    vm_shown.iter().with_id().for_each(|(id, _)| {
-        image_creator
+    viewer.reset();
+
+    // Remove all existing shown images, or fade them out if crossfading
+    let shown = vm_shown
+        .iter()
+        .with_id()
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+
+    shown.into_iter().for_each(|id| match event.fade {
+        Some(duration) => {
+            vm_shown.remove(id);
+            image_creator
+                .entities
+                .add_component(id, &mut vm_fade, ImageFade::fade_out(duration));
+        }
+        None => image_creator
             .entities
-            .add_component(id, &mut vm_remove, ToRemove)
+            .add_component(id, &mut vm_remove, ToRemove),
     });
 
     let id = match event.selected {
@@ -700,43 +1407,28 @@ fn sys_process_selected(
 
     let meta = ImageMeta {
         texture_resolution: texture.resolution,
+        color_converted: texture.info.color_converted,
     };
 
     let entity_id = match &texture.texture {
-        crate::storage::TextureType::Texture(texture) => {
-            let image = StandardImage {
-                id,
-                instance: Texture2dInstance::new(
-                    device.inner(),
-                    &texture_pipeline,
-                    Texture2dInstanceRaw::default(),
-                    texture,
-                ),
-            };
-
-            image_creator.spawn_image(image, meta)
+        crate::storage::TextureType::Texture(_) | crate::storage::TextureType::Hdr(_) => {
+            image_creator.spawn_image(StandardImage { id }, meta)
         }
-        crate::storage::TextureType::Gif { gif, frames } => {
-            let gif = GifImage {
-                id,
-                frame: 0,
-                total_frames: gif.total_frames,
-                frames_per_row: gif.frames_per_row,
-                instance: Gif2dInstance::new(
-                    device.inner(),
-                    &gif_pipeline,
-                    Gif2dInstanceRaw::default(),
-                    gif,
-                ),
-            };
-
-            image_creator.spawn_gif(gif, frames, meta)
+        crate::storage::TextureType::Gif(_) => {
+            image_creator.spawn_gif(GifImage { id, frame: None }, meta)
         }
     };
 
     image_creator
         .entities
         .add_component(entity_id, &mut vm_shown, ImageShown);
+
+    if let Some(duration) = event.fade {
+        (&mut image_creator.color).get(entity_id).unwrap().a = 0.;
+        image_creator
+            .entities
+            .add_component(entity_id, &mut vm_fade, ImageFade::fade_in(duration));
+    }
 }
 
 fn sys_set_layout_selected(events: Res<EventHandler>, mut layout: ResMut<LayoutManager>) {
@@ -744,32 +1436,33 @@ fn sys_set_layout_selected(events: Res<EventHandler>, mut layout: ResMut<LayoutM
 
     match event.selected {
         Some(_) => layout.selected = true,
-        None => layout.selected = false,
+        None => {
+            layout.selected = false;
+            layout.fullscreen = false;
+        }
     }
 }
 
 fn sys_resize_selected(
     window_size: Res<WindowSize>,
+    layout: Res<LayoutManager>,
+    viewer: Res<Viewer>,
+
+    entities: EntitiesView,
     v_shown: View<ImageShown>,
     mut vm_pos: ViewMut<Pos>,
     mut vm_size: ViewMut<ImageSize>,
     v_meta: View<ImageMeta>,
+    mut vm_dirty: ViewMut<ImageDirty>,
 ) {
+    let (pane_pos, pane_size) = layout.viewer_pane(&window_size);
+
     (&v_shown, &mut vm_pos, &mut vm_size, &v_meta)
         .iter()
-        .for_each(|(_, pos, size, meta)| {
-            let selected_viewport_width = window_size.width_f32() / 2.;
-            let selected_viewport_height = window_size.height_f32();
-
-            let wratio = selected_viewport_width / meta.texture_resolution.width as f32;
-            let hratio = selected_viewport_height / meta.texture_resolution.height as f32;
-            let ratio = f32::min(wratio, hratio);
-
-            size.width = meta.texture_resolution.width as f32 * ratio;
-            size.height = meta.texture_resolution.height as f32 * ratio;
-
-            pos.x = selected_viewport_width / 2.;
-            pos.y = 0.;
+        .with_id()
+        .for_each(|(id, (_, mut pos, mut size, meta))| {
+            viewer.place(pane_pos, pane_size, meta, &mut pos, &mut size);
+            entities.add_component(id, &mut vm_dirty, ImageDirty);
         });
 }
 
@@ -1,18 +1,47 @@
 //====================================================================
 
+use adjustments::AdjustmentsPlugin;
 use cabat::{runner::Runner, DefaultPlugins};
+use captions::CaptionPlugin;
+use config::ConfigPlugin;
 use debug::DebugPlugin;
+use decoration::DecorationPlugin;
+use exposure::ExposurePlugin;
+use folder_state::FolderStatePlugin;
+use grouping::GroupingPlugin;
 use images::ImagePlugin;
+use keybinds::KeybindsPlugin;
 use layout::LayoutPlugin;
+use playback::PlaybackPlugin;
 use renderer::CustomRendererPlugin;
+use scrollbar::ScrollbarPlugin;
+use selection::SelectionPlugin;
+use slideshow::SlideshowPlugin;
+use splitter::SplitterPlugin;
 use storage::StoragePlugin;
+use viewer::ViewerPlugin;
 
+pub(crate) mod adjustments;
+pub(crate) mod captions;
+pub(crate) mod config;
 pub(crate) mod debug;
+pub(crate) mod decoration;
+pub(crate) mod exposure;
+pub(crate) mod folder_state;
+pub(crate) mod grouping;
 pub(crate) mod images;
+pub(crate) mod keybinds;
 pub(crate) mod layout;
+pub(crate) mod playback;
 pub(crate) mod renderer;
+pub(crate) mod scrollbar;
+pub(crate) mod selection;
+pub(crate) mod slider;
+pub(crate) mod slideshow;
+pub(crate) mod splitter;
 pub(crate) mod storage;
 pub(crate) mod tools;
+pub(crate) mod viewer;
 
 //====================================================================
 
@@ -35,8 +64,22 @@ fn main() {
             // .add_plugin(RendererPlugin)
             .add_plugin(CustomRendererPlugin)
             .add_plugin(DebugPlugin)
+            .add_plugin(KeybindsPlugin)
+            .add_plugin(ConfigPlugin)
             .add_plugin(StoragePlugin)
             .add_plugin(LayoutPlugin)
+            .add_plugin(GroupingPlugin)
+            .add_plugin(CaptionPlugin)
+            .add_plugin(FolderStatePlugin)
+            .add_plugin(ViewerPlugin)
+            .add_plugin(SelectionPlugin)
+            .add_plugin(DecorationPlugin)
+            .add_plugin(ScrollbarPlugin)
+            .add_plugin(SplitterPlugin)
+            .add_plugin(SlideshowPlugin)
+            .add_plugin(PlaybackPlugin)
+            .add_plugin(ExposurePlugin)
+            .add_plugin(AdjustmentsPlugin)
             .add_plugin(ImagePlugin);
     });
 }
@@ -0,0 +1,448 @@
+//====================================================================
+
+use ahash::AHashSet;
+use cabat::{
+    common::WindowSize,
+    renderer::text::{Text2dBuffer, TextFontSystem},
+    runner::tools::{MouseInput, Time},
+    shipyard_tools::{prelude::*, UniqueTools},
+};
+use shipyard::{
+    AllStoragesView, EntitiesView, EntitiesViewMut, EntityId, Get, IntoIter, IntoWithId, Unique,
+    View, ViewMut,
+};
+
+use crate::{
+    config::{AnimateGifs, Settings, SettingsFile},
+    images::{GifImage, ImageDirty, ImageHovered, ImageIndex, ImageSelected, ImageShown, Pos},
+    keybinds::{Action, ActionInput},
+    layout::LayoutManager,
+    renderer::{
+        camera::UiCamera,
+        gif::GifFrameDelay,
+        rect_pipeline::{RectShape, UiRect},
+    },
+    slider::{Slider, Track},
+    slideshow::Slideshow,
+    storage::{Storage, TextureID, TextureType},
+};
+
+//====================================================================
+
+pub(crate) struct PlaybackPlugin;
+
+impl Plugin for PlaybackPlugin {
+    fn build(self, workload_builder: &WorkloadBuilder) {
+        workload_builder
+            .add_workload(Stages::Setup, sys_setup_playback)
+            .add_workload(
+                Stages::Update,
+                (
+                    sys_toggle_animate_gifs,
+                    sys_control_playback,
+                    sys_scrub_playback,
+                    sys_tick_playback,
+                    sys_freeze_grid_gifs,
+                )
+                    .into_sequential_workload(),
+            )
+            .add_workload_post(Stages::Update, sys_update_scrub_bar);
+    }
+}
+
+//====================================================================
+
+/// Playback of the gif shown in the viewer, driven from the cpu so it can be
+/// paused, stepped and scrubbed
+#[derive(Unique)]
+pub struct GifPlayback {
+    shown: Option<EntityId>,
+    paused: bool,
+
+    // Milliseconds into the gif's loop
+    position: f64,
+    speed: f32,
+    min_speed: f32,
+    max_speed: f32,
+
+    slider: Slider,
+
+    // Setting last applied to the grid's gifs, and the ones it lets play
+    grid_animate: AnimateGifs,
+    grid_playing: AHashSet<EntityId>,
+}
+
+impl GifPlayback {
+    /// Center and size of the scrub bar in screen space, along the bottom of the viewer pane
+    fn track(&self, layout: &LayoutManager, window_size: &WindowSize) -> Track {
+        let (pane_pos, pane_size) = layout.viewer_pane(window_size);
+        let margin = self.slider.margin();
+        let bar_height = self.slider.bar_height();
+
+        (
+            glam::vec2(
+                pane_pos.x,
+                -window_size.height_f32() / 2. + margin + bar_height / 2.,
+            ),
+            glam::vec2((pane_size.x - margin * 2.).max(0.), bar_height),
+        )
+    }
+
+    /// Whether the given screen space point is over the scrub bar
+    pub fn contains(
+        &self,
+        layout: &LayoutManager,
+        window_size: &WindowSize,
+        point: glam::Vec2,
+    ) -> bool {
+        self.shown.is_some() && self.slider.contains(self.track(layout, window_size), point)
+    }
+
+    fn step(&mut self, delay: &GifFrameDelay, forward: bool) {
+        let count = delay.frame_count().max(1);
+        let frame = delay.frame_at(self.position as u32);
+
+        let frame = match forward {
+            true => (frame + 1) % count,
+            false => (frame + count - 1) % count,
+        };
+
+        self.paused = true;
+        self.position = delay.frame_start(frame) as f64;
+    }
+}
+
+fn gif_delay(storage: &Storage, id: TextureID) -> Option<&GifFrameDelay> {
+    match &storage.get_texture(id)?.texture {
+        TextureType::Gif(gif) => Some(&gif.delay),
+        TextureType::Texture(_) | TextureType::Hdr(_) => None,
+    }
+}
+
+//====================================================================
+
+fn sys_setup_playback(
+    all_storages: AllStoragesView,
+    mut entities: EntitiesViewMut,
+    mut font_system: ResMut<TextFontSystem>,
+    settings: Res<Settings>,
+
+    mut vm_pos: ViewMut<Pos>,
+    mut vm_rect: ViewMut<RectShape>,
+    mut vm_ui: ViewMut<UiRect>,
+    mut vm_text: ViewMut<Text2dBuffer>,
+) {
+    let slider = Slider::new(
+        &mut entities,
+        &mut font_system,
+        &mut vm_pos,
+        &mut vm_rect,
+        &mut vm_ui,
+        &mut vm_text,
+    );
+
+    all_storages.add_unique(GifPlayback {
+        shown: None,
+        paused: false,
+
+        position: 0.,
+        speed: 1.,
+        min_speed: 0.25,
+        max_speed: 4.,
+
+        slider,
+
+        grid_animate: settings.appearance.animate_gifs,
+        grid_playing: AHashSet::new(),
+    });
+}
+
+fn sys_control_playback(
+    actions: ActionInput,
+    layout: Res<LayoutManager>,
+    slideshow: Res<Slideshow>,
+    storage: Res<Storage>,
+    mut playback: ResMut<GifPlayback>,
+
+    v_shown: View<ImageShown>,
+    v_gif: View<GifImage>,
+) {
+    let shown = (&v_shown, &v_gif)
+        .iter()
+        .with_id()
+        .next()
+        .and_then(|(id, (_, gif))| Some((id, gif_delay(&storage, gif.id)?)));
+
+    let (id, delay) = match (layout.selected(), shown) {
+        (true, Some(shown)) => shown,
+        _ => {
+            playback.shown = None;
+            playback.slider.stop_dragging();
+            return;
+        }
+    };
+
+    // Newly shown gifs start from the beginning
+    if playback.shown != Some(id) {
+        playback.shown = Some(id);
+        playback.paused = false;
+        playback.position = 0.;
+    }
+
+    // Space pauses the slideshow while it's running
+    if !slideshow.active() && actions.just_pressed(Action::GifPlayPause) {
+        playback.paused = !playback.paused;
+    }
+
+    if actions.just_pressed(Action::GifStepForward) {
+        playback.step(delay, true);
+    }
+
+    if actions.just_pressed(Action::GifStepBack) {
+        playback.step(delay, false);
+    }
+
+    if actions.just_pressed(Action::GifFaster) {
+        playback.speed = (playback.speed * 2.).min(playback.max_speed);
+        log::info!("Gif playback speed: {}x", playback.speed);
+    }
+
+    if actions.just_pressed(Action::GifSlower) {
+        playback.speed = (playback.speed / 2.).max(playback.min_speed);
+        log::info!("Gif playback speed: {}x", playback.speed);
+    }
+}
+
+fn sys_scrub_playback(
+    window_size: Res<WindowSize>,
+    layout: Res<LayoutManager>,
+    ui_camera: Res<UiCamera>,
+    storage: Res<Storage>,
+    mut playback: ResMut<GifPlayback>,
+
+    actions: ActionInput,
+    mouse: Res<MouseInput>,
+
+    v_gif: View<GifImage>,
+) {
+    let delay = match playback
+        .shown
+        .and_then(|id| (&v_gif).get(id).ok())
+        .and_then(|gif| gif_delay(&storage, gif.id))
+    {
+        Some(delay) => delay,
+        None => return,
+    };
+
+    let mouse_pos = ui_camera.raw.screen_to_camera(mouse.screen_pos());
+    let track = playback.track(&layout, &window_size);
+
+    let progress = match playback.slider.drag(track, &actions, mouse_pos) {
+        Some(progress) => progress,
+        None => return,
+    };
+
+    // Land on the start of a frame so the handle lines up with what's shown
+    let frame = delay
+        .frame_at((progress * delay.total() as f32) as u32)
+        .min(delay.frame_count().saturating_sub(1));
+
+    playback.position = delay.frame_start(frame) as f64;
+}
+
+fn sys_tick_playback(
+    time: Res<Time>,
+    storage: Res<Storage>,
+    mut playback: ResMut<GifPlayback>,
+
+    entities: EntitiesView,
+    mut vm_gif: ViewMut<GifImage>,
+    mut vm_dirty: ViewMut<ImageDirty>,
+) {
+    let id = match playback.shown {
+        Some(id) => id,
+        None => return,
+    };
+
+    let delay = match (&vm_gif)
+        .get(id)
+        .ok()
+        .and_then(|gif| gif_delay(&storage, gif.id))
+    {
+        Some(delay) => delay,
+        None => return,
+    };
+
+    let total = delay.total() as f64;
+
+    if !playback.paused && !playback.slider.dragging() && total > 0. {
+        let elapsed = time.delta().as_secs_f64() * 1000. * playback.speed as f64;
+        playback.position = (playback.position + elapsed) % total;
+    }
+
+    let frame = delay.frame_at(playback.position as u32);
+    hold_frame(id, Some(frame), &entities, &mut vm_gif, &mut vm_dirty);
+}
+
+fn sys_toggle_animate_gifs(
+    actions: ActionInput,
+    mut settings: ResMut<Settings>,
+    mut settings_file: ResMut<SettingsFile>,
+) {
+    if !actions.just_pressed(Action::ToggleAnimateGifs) {
+        return;
+    }
+
+    settings.appearance.animate_gifs = settings.appearance.animate_gifs.next();
+    settings_file.request_save();
+
+    log::info!("Animate gifs: {:?}", settings.appearance.animate_gifs);
+}
+
+/// Hold grid gifs on their first frame unless they're allowed to play. Only gifs
+/// whose hovered or selected state changed are touched.
+fn sys_freeze_grid_gifs(
+    settings: Res<Settings>,
+    mut playback: ResMut<GifPlayback>,
+
+    entities: EntitiesView,
+    v_index: View<ImageIndex>,
+    v_hovered: View<ImageHovered>,
+    v_selected: View<ImageSelected>,
+    mut vm_gif: ViewMut<GifImage>,
+    mut vm_dirty: ViewMut<ImageDirty>,
+) {
+    let animate = settings.appearance.animate_gifs;
+
+    // A new setting applies to every gif in the grid once
+    if playback.grid_animate != animate {
+        playback.grid_animate = animate;
+
+        let ids = (&vm_gif, &v_index)
+            .iter()
+            .with_id()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+
+        ids.into_iter().for_each(|id| {
+            let frame = animate.held_frame(v_hovered.contains(id) || v_selected.contains(id));
+            hold_frame(id, frame, &entities, &mut vm_gif, &mut vm_dirty);
+        });
+    }
+
+    if animate == AnimateGifs::All {
+        playback.grid_playing.clear();
+        return;
+    }
+
+    let playing = (&v_hovered, &vm_gif, &v_index)
+        .iter()
+        .with_id()
+        .map(|(id, _)| id)
+        .chain(
+            (&v_selected, &vm_gif, &v_index)
+                .iter()
+                .with_id()
+                .map(|(id, _)| id),
+        )
+        .collect::<AHashSet<_>>();
+
+    if playing == playback.grid_playing {
+        return;
+    }
+
+    let stopped = playback
+        .grid_playing
+        .difference(&playing)
+        .map(|id| (*id, Some(0)));
+    let started = playing
+        .difference(&playback.grid_playing)
+        .map(|id| (*id, None));
+
+    stopped.chain(started).for_each(|(id, frame)| {
+        hold_frame(id, frame, &entities, &mut vm_gif, &mut vm_dirty);
+    });
+
+    playback.grid_playing = playing;
+}
+
+/// Only marks the gif dirty when its frame changes
+fn hold_frame(
+    id: EntityId,
+    frame: Option<u32>,
+    entities: &EntitiesView,
+    vm_gif: &mut ViewMut<GifImage>,
+    vm_dirty: &mut ViewMut<ImageDirty>,
+) {
+    if let Ok(mut gif) = (&mut *vm_gif).get(id) {
+        if gif.frame != frame {
+            gif.frame = frame;
+            entities.add_component(id, vm_dirty, ImageDirty);
+        }
+    }
+}
+
+fn sys_update_scrub_bar(
+    window_size: Res<WindowSize>,
+    layout: Res<LayoutManager>,
+    storage: Res<Storage>,
+    mut playback: ResMut<GifPlayback>,
+    mut font_system: ResMut<TextFontSystem>,
+
+    v_gif: View<GifImage>,
+    mut vm_pos: ViewMut<Pos>,
+    mut vm_rect: ViewMut<RectShape>,
+    mut vm_text: ViewMut<Text2dBuffer>,
+) {
+    let delay = playback
+        .shown
+        .and_then(|id| (&v_gif).get(id).ok())
+        .and_then(|gif| gif_delay(&storage, gif.id));
+
+    let track = playback.track(&layout, &window_size);
+
+    let (frame, progress) = match delay {
+        Some(delay) if delay.total() > 0 => {
+            let frame = delay.frame_at(playback.position as u32);
+            (
+                frame,
+                delay.frame_start(frame) as f32 / delay.total() as f32,
+            )
+        }
+        _ => (0, 0.),
+    };
+
+    playback
+        .slider
+        .update_bar(track, progress, delay.is_some(), &mut vm_pos, &mut vm_rect);
+
+    let text = match delay {
+        Some(delay) => format!(
+            "Frame {}/{}   {} ms   {}x{}",
+            frame + 1,
+            delay.frame_count(),
+            delay.get_delay(frame).as_millis(),
+            playback.speed,
+            match playback.paused {
+                true => "   Paused",
+                false => "",
+            }
+        ),
+        None => String::new(),
+    };
+
+    // Just above the bar
+    let label_y =
+        window_size.height_f32() - playback.slider.margin() - playback.slider.bar_height() - 30.;
+
+    playback.slider.update_label(
+        track,
+        label_y,
+        text,
+        &window_size,
+        &mut font_system,
+        &mut vm_text,
+    );
+}
+
+//====================================================================
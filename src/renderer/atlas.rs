@@ -0,0 +1,416 @@
+//====================================================================
+
+use ahash::AHashMap;
+use image::RgbaImage;
+
+use crate::storage::TextureID;
+
+//====================================================================
+
+pub const ATLAS_PAGE_SIZE: u32 = 4096;
+pub const THUMBNAIL_SIZE: u32 = 256;
+
+// Gap left between regions so filtering doesn't pick up neighbouring thumbnails
+const REGION_PADDING: u32 = 1;
+
+//====================================================================
+
+#[derive(Clone, Copy, Debug)]
+pub struct AtlasRegion {
+    pub page: usize,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl AtlasRegion {
+    /// Offset and scale of the region in the page's uv space. Inset by half a
+    /// texel so edges don't sample the padding around them.
+    pub fn uv(&self) -> [f32; 4] {
+        let size = ATLAS_PAGE_SIZE as f32;
+
+        [
+            (self.x as f32 + 0.5) / size,
+            (self.y as f32 + 0.5) / size,
+            (self.width as f32 - 1.).max(0.) / size,
+            (self.height as f32 - 1.).max(0.) / size,
+        ]
+    }
+}
+
+//--------------------------------------------------
+
+struct Shelf {
+    y: u32,
+    height: u32,
+    // Start of the untouched space at the end of the shelf
+    cursor: u32,
+    // Gaps left by freed regions as (x, width)
+    free: Vec<(u32, u32)>,
+    regions: u32,
+}
+
+/// Packs regions into rows (shelves) of similar height. Freed regions leave a
+/// gap that later regions of the same height can reuse, and shelves are reset
+/// once everything on them has been freed.
+struct ShelfPacker {
+    size: u32,
+    shelves: Vec<Shelf>,
+}
+
+impl ShelfPacker {
+    fn new(size: u32) -> Self {
+        Self {
+            size,
+            shelves: Vec::new(),
+        }
+    }
+
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if width > self.size || height > self.size {
+            return None;
+        }
+
+        // Don't waste more than half a shelf on a short region
+        let fits =
+            |shelf_height: u32| shelf_height >= height && shelf_height <= height + height / 2;
+
+        let best = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| fits(shelf.height))
+            .min_by_key(|shelf| shelf.height);
+
+        if let Some(shelf) = best {
+            let gap = shelf.free.iter().position(|(_, gap)| *gap >= width);
+
+            if let Some(index) = gap {
+                let (x, gap_width) = shelf.free[index];
+
+                match gap_width == width {
+                    true => {
+                        shelf.free.swap_remove(index);
+                    }
+                    false => shelf.free[index] = (x + width, gap_width - width),
+                }
+
+                shelf.regions += 1;
+                return Some((x, shelf.y));
+            }
+
+            if shelf.cursor + width <= self.size {
+                let x = shelf.cursor;
+                shelf.cursor += width;
+                shelf.regions += 1;
+                return Some((x, shelf.y));
+            }
+        }
+
+        // Other shelves may still have space at their end
+        if let Some(shelf) = self
+            .shelves
+            .iter_mut()
+            .find(|shelf| fits(shelf.height) && shelf.cursor + width <= self.size)
+        {
+            let x = shelf.cursor;
+            shelf.cursor += width;
+            shelf.regions += 1;
+            return Some((x, shelf.y));
+        }
+
+        let y = self
+            .shelves
+            .last()
+            .map(|shelf| shelf.y + shelf.height)
+            .unwrap_or(0);
+
+        if y + height > self.size {
+            return None;
+        }
+
+        self.shelves.push(Shelf {
+            y,
+            height,
+            cursor: width,
+            free: Vec::new(),
+            regions: 1,
+        });
+
+        Some((0, y))
+    }
+
+    fn free(&mut self, x: u32, y: u32, width: u32) {
+        let shelf = match self.shelves.iter_mut().find(|shelf| shelf.y == y) {
+            Some(shelf) => shelf,
+            None => return,
+        };
+
+        shelf.regions = shelf.regions.saturating_sub(1);
+
+        match shelf.regions {
+            0 => {
+                shelf.cursor = 0;
+                shelf.free.clear();
+            }
+            _ => match x + width == shelf.cursor {
+                true => shelf.cursor = x,
+                false => shelf.free.push((x, width)),
+            },
+        }
+
+        // Empty shelves at the bottom can be taken by regions of any height
+        while self.shelves.last().is_some_and(|shelf| shelf.regions == 0) {
+            self.shelves.pop();
+        }
+    }
+}
+
+//====================================================================
+
+struct AtlasPage {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    packer: ShelfPacker,
+}
+
+/// Thumbnails of standard images packed into a few large textures so the grid
+/// can be drawn with a handful of bind groups.
+pub struct Atlas {
+    sampler: wgpu::Sampler,
+
+    pages: Vec<AtlasPage>,
+    regions: AHashMap<TextureID, AtlasRegion>,
+}
+
+impl Atlas {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Atlas Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            sampler,
+            pages: Vec::new(),
+            regions: AHashMap::new(),
+        }
+    }
+
+    #[inline]
+    pub fn region(&self, id: TextureID) -> Option<&AtlasRegion> {
+        self.regions.get(&id)
+    }
+
+    #[inline]
+    pub fn bind_group(&self, page: usize) -> Option<&wgpu::BindGroup> {
+        self.pages.get(page).map(|page| &page.bind_group)
+    }
+
+    pub fn insert(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        id: TextureID,
+        image: &RgbaImage,
+    ) -> Option<AtlasRegion> {
+        self.remove(id);
+
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let padded = (width + REGION_PADDING, height + REGION_PADDING);
+
+        let found = self.pages.iter_mut().enumerate().find_map(|(index, page)| {
+            page.packer
+                .allocate(padded.0, padded.1)
+                .map(|pos| (index, pos))
+        });
+
+        let (page, (x, y)) = match found {
+            Some(found) => found,
+            None => {
+                let mut page = self.create_page(device, layout);
+                let pos = page.packer.allocate(padded.0, padded.1)?;
+
+                self.pages.push(page);
+                log::debug!("Created atlas page {}", self.pages.len());
+
+                (self.pages.len() - 1, pos)
+            }
+        };
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.pages[page].texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            image.as_raw(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        let region = AtlasRegion {
+            page,
+            x,
+            y,
+            width,
+            height,
+        };
+
+        self.regions.insert(id, region);
+        Some(region)
+    }
+
+    /// Free the space used by a thumbnail so other thumbnails can use it
+    pub fn remove(&mut self, id: TextureID) {
+        let region = match self.regions.remove(&id) {
+            Some(region) => region,
+            None => return,
+        };
+
+        if let Some(page) = self.pages.get_mut(region.page) {
+            page.packer
+                .free(region.x, region.y, region.width + REGION_PADDING);
+        }
+    }
+
+    fn create_page(&self, device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> AtlasPage {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Atlas Page"),
+            size: wgpu::Extent3d {
+                width: ATLAS_PAGE_SIZE,
+                height: ATLAS_PAGE_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Atlas Page Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+
+        AtlasPage {
+            texture,
+            bind_group,
+            packer: ShelfPacker::new(ATLAS_PAGE_SIZE),
+        }
+    }
+}
+
+//====================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocates_along_shelves() {
+        let mut packer = ShelfPacker::new(100);
+
+        assert_eq!(packer.allocate(40, 20), Some((0, 0)));
+        assert_eq!(packer.allocate(40, 20), Some((40, 0)));
+        // Too wide for what's left of the first shelf
+        assert_eq!(packer.allocate(40, 20), Some((0, 20)));
+        // Too short to share a shelf with the 20 high regions
+        assert_eq!(packer.allocate(10, 10), Some((0, 40)));
+    }
+
+    #[test]
+    fn rejects_what_doesnt_fit() {
+        let mut packer = ShelfPacker::new(100);
+
+        assert_eq!(packer.allocate(101, 10), None);
+        assert_eq!(packer.allocate(10, 101), None);
+
+        assert_eq!(packer.allocate(100, 60), Some((0, 0)));
+        assert_eq!(packer.allocate(100, 60), None);
+    }
+
+    #[test]
+    fn reuses_freed_gaps() {
+        let mut packer = ShelfPacker::new(100);
+
+        assert_eq!(packer.allocate(30, 20), Some((0, 0)));
+        assert_eq!(packer.allocate(30, 20), Some((30, 0)));
+        assert_eq!(packer.allocate(30, 20), Some((60, 0)));
+
+        packer.free(30, 0, 30);
+
+        // A narrower region takes the start of the gap, leaving the rest free
+        assert_eq!(packer.allocate(20, 20), Some((30, 0)));
+        assert_eq!(packer.allocate(10, 20), Some((50, 0)));
+        assert_eq!(packer.allocate(10, 20), Some((90, 0)));
+        assert_eq!(packer.allocate(10, 20), Some((0, 20)));
+    }
+
+    #[test]
+    fn frees_the_end_of_a_shelf() {
+        let mut packer = ShelfPacker::new(100);
+
+        assert_eq!(packer.allocate(50, 20), Some((0, 0)));
+        assert_eq!(packer.allocate(50, 20), Some((50, 0)));
+
+        // Freeing the last region moves the cursor back instead of leaving a gap
+        packer.free(50, 0, 50);
+        assert_eq!(packer.allocate(50, 20), Some((50, 0)));
+    }
+
+    #[test]
+    fn resets_empty_shelves() {
+        let mut packer = ShelfPacker::new(100);
+
+        assert_eq!(packer.allocate(50, 20), Some((0, 0)));
+        assert_eq!(packer.allocate(50, 20), Some((50, 0)));
+        assert_eq!(packer.allocate(50, 40), Some((0, 20)));
+
+        packer.free(0, 20, 50);
+
+        // The empty bottom shelf is dropped and can be taken at any height
+        assert_eq!(packer.allocate(100, 80), Some((0, 20)));
+
+        packer.free(0, 0, 50);
+        packer.free(50, 0, 50);
+        packer.free(0, 20, 100);
+
+        // Everything freed, the whole page is available again
+        assert_eq!(packer.allocate(100, 100), Some((0, 0)));
+    }
+}
+
+//====================================================================
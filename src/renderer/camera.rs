@@ -55,7 +55,7 @@ pub(super) fn sys_update_camera(
     if ui_camera.is_modified() {
         ui_camera
             .camera
-            .update_camera(queue.inner(), &main_camera.raw)
+            .update_camera(queue.inner(), &ui_camera.raw)
     }
 }
 
@@ -34,7 +34,7 @@ impl Vertex for RawVertex {
     }
 }
 
-const VERTICES: [RawVertex; 4] = [
+pub const VERTICES: [RawVertex; 4] = [
     RawVertex { pos: [-0.5, 0.5] },
     RawVertex { pos: [-0.5, -0.5] },
     RawVertex { pos: [0.5, 0.5] },
@@ -1,6 +1,6 @@
 //====================================================================
 
-use std::{collections::HashMap, ops::Range, time::Duration};
+use std::time::Duration;
 
 use cabat::renderer::texture;
 use image::DynamicImage;
@@ -14,68 +14,97 @@ pub const MAX_TEXTURE_HEIGHT: u32 = 8192;
 pub const MAX_USABLE_IMAGE_WIDTH: u32 = 1920 / 2;
 pub const MAX_USABLE_IMAGE_HEIGHT: u32 = 1080;
 
+// Browsers play frames with a delay of 10ms or less at 100ms instead
+const MIN_FRAME_DELAY_MS: u32 = 10;
+const CLAMPED_FRAME_DELAY_MS: u32 = 100;
+
 //====================================================================
 
+/// End time of each frame in milliseconds, counted from the start of the loop.
+/// Playback is driven by total elapsed time rather than per-frame timers, so
+/// leftover time carries into the next frame and long hitches skip ahead
+/// however many frames they cover.
 pub struct GifFrameDelay {
-    delays: HashMap<Range<u32>, Duration>,
+    frame_ends: Vec<u32>,
 }
 
 impl GifFrameDelay {
-    pub fn from_durations(delays: &Vec<Duration>) -> Self {
+    pub fn from_durations(delays: &[Duration]) -> Self {
         if delays.is_empty() {
             log::warn!("Gif Frame Delay created with zero length vector");
-            return Self {
-                delays: HashMap::new(),
-            };
         }
 
-        let mut delays_final = HashMap::new();
-        let mut start_index = 0;
-        let mut prev = delays[0];
-
-        delays
+        let mut end = 0u32;
+        let frame_ends = delays
             .iter()
-            .enumerate()
-            .skip(1)
-            .for_each(|(index, delay)| {
-                if *delay == prev {
-                    return;
-                }
+            .map(|delay| {
+                let delay = match delay.as_millis() as u32 {
+                    delay if delay <= MIN_FRAME_DELAY_MS => CLAMPED_FRAME_DELAY_MS,
+                    delay => delay,
+                };
 
-                let index = index as u32;
-                delays_final.insert(start_index..index, prev);
+                end = end.saturating_add(delay);
+                end
+            })
+            .collect();
+
+        Self { frame_ends }
+    }
 
-                start_index = index;
-                prev = *delay;
-            });
+    #[inline]
+    pub fn frame_ends(&self) -> &[u32] {
+        &self.frame_ends
+    }
 
-        let final_index = delays.len() as u32;
+    #[inline]
+    pub fn frame_count(&self) -> u32 {
+        self.frame_ends.len() as u32
+    }
 
-        delays_final.insert(start_index..final_index, prev);
+    /// Length of one loop in milliseconds
+    #[inline]
+    pub fn total(&self) -> u32 {
+        self.frame_ends.last().copied().unwrap_or(0)
+    }
 
-        Self {
-            delays: delays_final,
+    /// Time into the loop the given frame starts at, in milliseconds
+    pub fn frame_start(&self, frame: u32) -> u32 {
+        match frame {
+            0 => 0,
+            frame => self
+                .frame_ends
+                .get(frame as usize - 1)
+                .copied()
+                .unwrap_or(self.total()),
         }
     }
 
-    pub fn get_delay(&self, frame: &u32) -> Duration {
-        let val = self.delays.iter().find(|(key, _)| key.contains(frame));
+    pub fn get_delay(&self, frame: u32) -> Duration {
+        let end = self.frame_ends.get(frame as usize).copied().unwrap_or(0);
+        Duration::from_millis(end.saturating_sub(self.frame_start(frame)) as u64)
+    }
 
-        match val {
-            Some((_, key)) => *key,
-            None => {
-                log::warn!("Get delay: frame {} out of range", frame);
-                Duration::ZERO
-            }
+    /// Frame shown at the given time into the loop, wrapping past the end.
+    /// Binary searches the frame end times.
+    pub fn frame_at(&self, time: u32) -> u32 {
+        let total = self.total();
+        if total == 0 {
+            return 0;
         }
+
+        let time = time % total;
+
+        self.frame_ends.partition_point(|end| *end <= time) as u32
     }
 }
 
 pub struct Gif {
     pub texture: texture::RawTexture,
     pub buffer: wgpu::Buffer,
+    // Frame delay table, read by the shader to pick the current frame
+    pub frame_ends: wgpu::Buffer,
+    pub delay: GifFrameDelay,
     pub total_frames: u32,
-    pub frames_per_row: u32,
 }
 
 #[repr(C)]
@@ -99,6 +128,7 @@ impl Gif {
         total_rows: u32,
         frame_width: u32,
         frame_height: u32,
+        delay: GifFrameDelay,
     ) -> Self {
         let texture = texture::RawTexture::from_image(device, queue, &image, None, None);
 
@@ -121,13 +151,199 @@ impl Gif {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
+        // Storage buffers can't be empty
+        let frame_ends = match delay.frame_ends().is_empty() {
+            true => &[0][..],
+            false => delay.frame_ends(),
+        };
+
+        let frame_ends = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} gif frame buffer", label)),
+            contents: bytemuck::cast_slice(frame_ends),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
         Self {
             texture,
             buffer,
+            frame_ends,
+            delay,
             total_frames,
-            frames_per_row,
         }
     }
 }
 
 //====================================================================
+
+#[cfg(test)]
+mod tests {
+    use image::{codecs::gif::GifDecoder, AnimationDecoder};
+
+    use super::*;
+
+    // Looping 1x1 gif with four frames held for 10, 4, 0 and 7 hundredths of a second
+    #[rustfmt::skip]
+    const LOOPING_GIF: &[u8] = &[
+        // Header, 1x1 screen with a two colour global palette
+        b'G', b'I', b'F', b'8', b'9', b'a', 1, 0, 1, 0, 0x80, 0, 0,
+        0, 0, 0, 255, 255, 255,
+        // Loop forever
+        0x21, 0xff, 11, b'N', b'E', b'T', b'S', b'C', b'A', b'P', b'E', b'2', b'.', b'0',
+        3, 1, 0, 0, 0,
+        // Graphic control extension with the delay, then the frame itself
+        0x21, 0xf9, 4, 0, 10, 0, 0, 0, 0x2c, 0, 0, 0, 0, 1, 0, 1, 0, 0, 2, 2, 0x44, 0x01, 0,
+        0x21, 0xf9, 4, 0, 4, 0, 0, 0, 0x2c, 0, 0, 0, 0, 1, 0, 1, 0, 0, 2, 2, 0x44, 0x01, 0,
+        0x21, 0xf9, 4, 0, 0, 0, 0, 0, 0x2c, 0, 0, 0, 0, 1, 0, 1, 0, 0, 2, 2, 0x44, 0x01, 0,
+        0x21, 0xf9, 4, 0, 7, 0, 0, 0, 0x2c, 0, 0, 0, 0, 1, 0, 1, 0, 0, 2, 2, 0x44, 0x01, 0,
+        0x3b,
+    ];
+
+    fn delays(ms: &[u64]) -> GifFrameDelay {
+        GifFrameDelay::from_durations(
+            &ms.iter()
+                .map(|ms| Duration::from_millis(*ms))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Delays read the same way as when loading gifs
+    fn decoded_delays(data: &[u8]) -> GifFrameDelay {
+        let frames = GifDecoder::new(std::io::Cursor::new(data))
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+
+        GifFrameDelay::from_durations(
+            &frames
+                .iter()
+                .map(|frame| Duration::from_millis(frame.delay().numer_denom_ms().0 as u64))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Frame shown after each tick, advancing the position like sys_tick_playback
+    fn play(delay: &GifFrameDelay, ticks: usize, tick_ms: f64, speed: f32) -> Vec<u32> {
+        let total = delay.total() as f64;
+        let mut position = 0.;
+
+        (0..ticks)
+            .map(|_| {
+                position = (position + tick_ms * speed as f64) % total;
+                delay.frame_at(position as u32)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn clamps_short_delays() {
+        let delay = delays(&[0, 10, 20, 100]);
+
+        assert_eq!(delay.get_delay(0), Duration::from_millis(100));
+        assert_eq!(delay.get_delay(1), Duration::from_millis(100));
+        assert_eq!(delay.get_delay(2), Duration::from_millis(20));
+        assert_eq!(delay.get_delay(3), Duration::from_millis(100));
+        assert_eq!(delay.total(), 320);
+    }
+
+    #[test]
+    fn frame_boundaries() {
+        let delay = delays(&[100, 50, 250]);
+
+        assert_eq!(delay.frame_ends(), &[100, 150, 400]);
+        assert_eq!(delay.frame_at(0), 0);
+        assert_eq!(delay.frame_at(99), 0);
+        assert_eq!(delay.frame_at(100), 1);
+        assert_eq!(delay.frame_at(149), 1);
+        assert_eq!(delay.frame_at(150), 2);
+        assert_eq!(delay.frame_at(399), 2);
+
+        assert_eq!(delay.frame_start(0), 0);
+        assert_eq!(delay.frame_start(1), 100);
+        assert_eq!(delay.frame_start(2), 150);
+    }
+
+    #[test]
+    fn carries_leftover_time() {
+        // 60 fps ticks against 30ms frames, a per-frame timer that reset on
+        // advance would drift a tick behind every frame
+        let delay = delays(&[30; 10]);
+        let tick = 1000. / 60.;
+
+        assert_eq!(delay.frame_at((tick * 9.) as u32), 5);
+        assert_eq!(delay.frame_at((tick * 15.) as u32), 8);
+        // A full loop after 18 ticks
+        assert_eq!(delay.frame_at((tick * 18.) as u32), 0);
+    }
+
+    #[test]
+    fn skips_frames_on_hitch() {
+        let delay = delays(&[40, 40, 40, 40, 40]);
+
+        // A single 130ms update lands three frames on
+        assert_eq!(delay.frame_at(130), 3);
+        assert_eq!(delay.frame_at(5 + 130), 3);
+    }
+
+    #[test]
+    fn wraps_past_the_end() {
+        let delay = delays(&[100, 200]);
+
+        assert_eq!(delay.frame_at(300), 0);
+        assert_eq!(delay.frame_at(399), 0);
+        assert_eq!(delay.frame_at(400), 1);
+        assert_eq!(delay.frame_at(300 * 1000 + 150), 1);
+    }
+
+    #[test]
+    fn plays_decoded_gif() {
+        let delay = decoded_delays(LOOPING_GIF);
+
+        // The 0 delay frame is held for 100ms like browsers do
+        assert_eq!(delay.frame_ends(), &[100, 140, 240, 310]);
+
+        assert_eq!(play(&delay, 8, 50., 1.), [0, 1, 2, 2, 3, 3, 0, 0]);
+        // Double speed covers 40ms a tick and wraps a few ms into the next loop
+        assert_eq!(play(&delay, 9, 20., 2.), [0, 0, 1, 2, 2, 3, 3, 0, 0]);
+    }
+
+    #[test]
+    fn plays_decoded_gif_slowed_down() {
+        let delay = decoded_delays(LOOPING_GIF);
+
+        // Half speed with 20ms ticks moves 10ms a tick
+        let expected = [(0, 9), (1, 4), (2, 10), (3, 7), (0, 1)]
+            .into_iter()
+            .flat_map(|(frame, ticks)| std::iter::repeat(frame).take(ticks))
+            .collect::<Vec<_>>();
+
+        assert_eq!(play(&delay, 31, 20., 0.5), expected);
+    }
+
+    #[test]
+    fn plays_decoded_gif_sped_up_at_60_fps() {
+        let delay = decoded_delays(LOOPING_GIF);
+
+        // One second at 1.5x is 1500ms in, 260ms into the fifth loop
+        let frames = play(&delay, 60, 1000. / 60., 1.5);
+        assert_eq!(frames.last(), Some(&3));
+
+        // 25ms ticks never skip a frame, each shows up in order
+        let mut changes = frames.clone();
+        changes.dedup();
+        assert_eq!(changes[..8], [0, 1, 2, 3, 0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn empty_and_single_frame() {
+        let empty = delays(&[]);
+        assert_eq!(empty.total(), 0);
+        assert_eq!(empty.frame_at(1234), 0);
+
+        let single = delays(&[70]);
+        assert_eq!(single.frame_at(0), 0);
+        assert_eq!(single.frame_at(1234), 0);
+    }
+}
+
+//====================================================================
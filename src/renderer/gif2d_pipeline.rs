@@ -1,16 +1,31 @@
 //====================================================================
 
-use cabat::renderer::{
-    render_tools,
-    shared::{
-        TextureRectVertex, TEXTURE_RECT_INDEX_COUNT, TEXTURE_RECT_INDICES, TEXTURE_RECT_VERTICES,
+use std::time::Duration;
+
+use ahash::AHashMap;
+use cabat::{
+    renderer::{
+        render_tools,
+        shared::{
+            TextureRectVertex, TEXTURE_RECT_INDEX_COUNT, TEXTURE_RECT_INDICES,
+            TEXTURE_RECT_VERTICES,
+        },
+        Device, Queue, Vertex,
     },
-    Vertex,
+    runner::tools::Time,
+    shipyard_tools::{Res, ResMut},
 };
-use shipyard::Unique;
+use shipyard::{IntoIter, Unique, View};
 use wgpu::util::DeviceExt;
 
-use super::gif::Gif;
+use super::{gif::Gif, instance_batch::InstanceBatches, texture2d_pipeline::CHECKER_SIZE};
+use crate::{
+    adjustments::{Adjustments, ImageAdjustments},
+    config::Settings,
+    images::{Color, GifImage, ImageDirty, ImageIndex, ImageSize, ImageVisible, Pos},
+    layout::LayoutManager,
+    storage::TextureID,
+};
 
 //====================================================================
 
@@ -20,53 +35,42 @@ pub struct Gif2dInstanceRaw {
     pub pos: [f32; 2],
     pub size: [f32; 2],
     pub color: [f32; 4],
-    pub frame_x: f32,
-    pub frame_y: f32,
-    pub padding: [u32; 2],
-}
-
-pub struct Gif2dInstance {
-    bind_group: wgpu::BindGroup,
-    buffer: wgpu::Buffer,
-
-    texture_bind_group: wgpu::BindGroup,
+    // Held frame, or -1 to follow the clock
+    pub frame: i32,
+    pub checker: f32,
+    // Brightness, contrast, gamma and invert, then the isolated channel
+    pub adjust: [f32; 4],
+    pub channel: u32,
 }
 
-impl Gif2dInstance {
-    pub fn new(
-        device: &wgpu::Device,
-        pipeline: &Gif2dPipeline,
-        data: Gif2dInstanceRaw,
-        gif: &Gif,
-    ) -> Self {
-        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Texture Instance"),
-            contents: bytemuck::cast_slice(&[data]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &pipeline.texture_instance_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::Buffer(buffer.as_entire_buffer_binding()),
-            }],
-        });
-
-        let texture_bind_group = pipeline.load_texture(&device, gif);
+impl Vertex for Gif2dInstanceRaw {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 7] = wgpu::vertex_attr_array![
+            2 => Float32x2, 3 => Float32x2, 4 => Float32x4, 5 => Sint32, 6 => Float32,
+            7 => Float32x4, 8 => Uint32,
+        ];
 
-        Self {
-            texture_bind_group,
-            bind_group,
-            buffer,
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Gif2dInstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &VERTEX_ATTRIBUTES,
         }
     }
+}
 
-    #[inline]
-    pub fn update(&self, queue: &wgpu::Queue, data: Gif2dInstanceRaw) {
-        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[data]));
-    }
+/// Everything besides the gifs themselves that goes into their instances
+#[derive(Clone, Copy, PartialEq)]
+struct InstanceParams {
+    fullscreen: bool,
+    checker: f32,
+}
+
+/// Time all gifs are animated against, in milliseconds
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod, Default)]
+struct GifClockRaw {
+    elapsed: u32,
+    padding: [u32; 3],
 }
 
 //====================================================================
@@ -75,11 +79,24 @@ impl Gif2dInstance {
 pub struct Gif2dPipeline {
     pipeline: wgpu::RenderPipeline,
     texture_bind_group_layout: wgpu::BindGroupLayout,
-    pub texture_instance_bind_group_layout: wgpu::BindGroupLayout,
 
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     index_count: u32,
+
+    textures: AHashMap<TextureID, wgpu::BindGroup>,
+
+    elapsed: Duration,
+    clock_buffer: wgpu::Buffer,
+    clock_bind_group: wgpu::BindGroup,
+
+    grid: InstanceBatches<TextureID>,
+    viewer: InstanceBatches<TextureID>,
+
+    // Instances are only rebuilt when gifs are dirty, textures change or the
+    // params they were built with change
+    rebuild: bool,
+    params: Option<InstanceParams>,
 }
 
 impl Gif2dPipeline {
@@ -97,16 +114,26 @@ impl Gif2dPipeline {
                 entries: &[
                     render_tools::bgl_texture_entry(0),
                     render_tools::bgl_sampler_entry(1),
-                    render_tools::bgl_uniform_entry(2, wgpu::ShaderStages::FRAGMENT),
+                    render_tools::bgl_uniform_entry(2, wgpu::ShaderStages::VERTEX_FRAGMENT),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
                 ],
             });
 
-        let texture_instance_bind_group_layout =
+        let clock_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Gif2d Instance Bind Group Layout"),
+                label: Some("Gif2d Clock Bind Group Layout"),
                 entries: &[render_tools::bgl_uniform_entry(
                     0,
-                    wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    wgpu::ShaderStages::VERTEX,
                 )],
             });
 
@@ -117,10 +144,13 @@ impl Gif2dPipeline {
             &[
                 camera_bind_group_layout,
                 &texture_bind_group_layout,
-                &texture_instance_bind_group_layout,
+                &clock_bind_group_layout,
             ],
-            &[TextureRectVertex::desc()],
-            include_str!("gif2d_shader.wgsl"),
+            &[TextureRectVertex::desc(), Gif2dInstanceRaw::desc()],
+            concat!(
+                include_str!("image_color.wgsl"),
+                include_str!("gif2d_shader.wgsl")
+            ),
             render_tools::RenderPipelineDescriptor::default().with_depth_stencil(),
         );
 
@@ -131,18 +161,40 @@ impl Gif2dPipeline {
             render_tools::index_buffer(&device, "Gif2d Pipeline", &TEXTURE_RECT_INDICES);
         let index_count = TEXTURE_RECT_INDEX_COUNT;
 
+        let clock_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Gif2d Clock Buffer"),
+            contents: bytemuck::cast_slice(&[GifClockRaw::default()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let clock_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Gif2d Clock Bind Group"),
+            layout: &clock_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(clock_buffer.as_entire_buffer_binding()),
+            }],
+        });
+
         Self {
             pipeline,
             texture_bind_group_layout,
-            texture_instance_bind_group_layout,
             vertex_buffer,
             index_buffer,
             index_count,
+            textures: AHashMap::new(),
+            elapsed: Duration::ZERO,
+            clock_buffer,
+            clock_bind_group,
+            grid: InstanceBatches::new(device, "Gif2d Pipeline Grid Instance Buffer"),
+            viewer: InstanceBatches::new(device, "Gif2d Pipeline Viewer Instance Buffer"),
+            rebuild: false,
+            params: None,
         }
     }
 
-    pub fn load_texture(&self, device: &wgpu::Device, data: &Gif) -> wgpu::BindGroup {
-        device.create_bind_group(&wgpu::BindGroupDescriptor {
+    pub fn add_gif(&mut self, device: &wgpu::Device, id: TextureID, data: &Gif) {
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Gif2dBindGroup"),
             layout: &self.texture_bind_group_layout,
             entries: &[
@@ -158,29 +210,147 @@ impl Gif2dPipeline {
                     binding: 2,
                     resource: wgpu::BindingResource::Buffer(data.buffer.as_entire_buffer_binding()),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Buffer(
+                        data.frame_ends.as_entire_buffer_binding(),
+                    ),
+                },
             ],
-        })
+        });
+
+        self.textures.insert(id, bind_group);
+        self.rebuild = true;
+    }
+
+    #[inline]
+    pub fn remove_gif(&mut self, id: TextureID) {
+        self.textures.remove(&id);
+        self.rebuild = true;
     }
 
-    pub fn render<'a, I: Iterator<Item = &'a Gif2dInstance>>(
-        &self,
-        pass: &mut wgpu::RenderPass,
-        camera_bind_goup: &wgpu::BindGroup,
-        to_render: I,
-    ) {
+    #[inline]
+    pub fn has_viewer_images(&self) -> bool {
+        !self.viewer.is_empty()
+    }
+
+    fn prepare(&self, pass: &mut wgpu::RenderPass, camera_bind_goup: &wgpu::BindGroup) {
         pass.set_pipeline(&self.pipeline);
         pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
 
         pass.set_bind_group(0, camera_bind_goup, &[]);
+        pass.set_bind_group(2, &self.clock_bind_group, &[]);
+    }
 
-        to_render.for_each(|to_render| {
-            pass.set_bind_group(1, &to_render.texture_bind_group, &[]);
-            pass.set_bind_group(2, &to_render.bind_group, &[]);
+    fn tick(&mut self, queue: &wgpu::Queue, delta: Duration) {
+        self.elapsed += delta;
 
-            pass.draw_indexed(0..self.index_count, 0, 0..1);
-        });
+        let clock = GifClockRaw {
+            // Wraps after ~49 days, which only costs a skipped frame
+            elapsed: self.elapsed.as_millis() as u32,
+            ..Default::default()
+        };
+
+        queue.write_buffer(&self.clock_buffer, 0, bytemuck::cast_slice(&[clock]));
+    }
+
+    pub fn render(&self, pass: &mut wgpu::RenderPass, camera_bind_goup: &wgpu::BindGroup) {
+        self.prepare(pass, camera_bind_goup);
+        self.grid
+            .render(pass, self.index_count, |id| self.textures.get(&id));
+    }
+
+    pub fn render_viewer(&self, pass: &mut wgpu::RenderPass, camera_bind_goup: &wgpu::BindGroup) {
+        self.prepare(pass, camera_bind_goup);
+        self.viewer
+            .render(pass, self.index_count, |id| self.textures.get(&id));
+    }
+}
+
+//====================================================================
+
+pub(super) fn sys_update_gif_pipeline(
+    device: Res<Device>,
+    queue: Res<Queue>,
+    time: Res<Time>,
+    layout: Res<LayoutManager>,
+    settings: Res<Settings>,
+    adjustments: Res<Adjustments>,
+    mut pipeline: ResMut<Gif2dPipeline>,
+
+    v_gif: View<GifImage>,
+    v_pos: View<Pos>,
+    v_size: View<ImageSize>,
+    v_color: View<Color>,
+    v_index: View<ImageIndex>,
+    v_visible: View<ImageVisible>,
+    v_dirty: View<ImageDirty>,
+) {
+    // Frames are picked in the shader, so the clock is all that's written every frame
+    pipeline.tick(queue.inner(), *time.delta());
+
+    let params = InstanceParams {
+        fullscreen: layout.fullscreen(),
+        checker: match settings.appearance.transparency_checker {
+            true => CHECKER_SIZE,
+            false => 0.,
+        },
+    };
+
+    if !pipeline.rebuild && v_dirty.is_empty() && pipeline.params == Some(params) {
+        return;
     }
+
+    pipeline.rebuild = false;
+    pipeline.params = Some(params);
+
+    let checker = params.checker;
+
+    let raw = |gif: &GifImage,
+               pos: &Pos,
+               size: &ImageSize,
+               color: &Color,
+               adjustments: ImageAdjustments| {
+        let (adjust, channel) = adjustments.raw();
+
+        Gif2dInstanceRaw {
+            pos: pos.to_array(),
+            size: size.to_array(),
+            color: color.to_array(),
+            frame: gif.frame.map(|frame| frame as i32).unwrap_or(-1),
+            checker,
+            adjust,
+            channel,
+        }
+    };
+
+    let mut grid = match params.fullscreen {
+        true => Vec::new(),
+        false => (&v_gif, &v_pos, &v_size, &v_color, &v_index, &v_visible)
+            .iter()
+            .map(|(gif, pos, size, color, _, _)| {
+                (
+                    gif.id,
+                    raw(gif, pos, size, color, ImageAdjustments::default()),
+                )
+            })
+            .collect::<Vec<_>>(),
+    };
+    grid.sort_by_key(|(id, _)| *id);
+
+    let viewer = (&v_gif, &v_pos, &v_size, &v_color, !&v_index)
+        .iter()
+        .map(|(gif, pos, size, color, _)| {
+            (gif.id, raw(gif, pos, size, color, adjustments.get(gif.id)))
+        })
+        .collect::<Vec<_>>();
+
+    let pipeline = &mut *pipeline;
+    pipeline.grid.update(device.inner(), queue.inner(), &grid);
+    pipeline
+        .viewer
+        .update(device.inner(), queue.inner(), &viewer);
 }
 
 //====================================================================
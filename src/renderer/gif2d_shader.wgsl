@@ -14,21 +14,20 @@ struct Frames {
     sample_height: f32,
 }
 
-struct TextureInstance {
-    pos: vec2<f32>,
-    size: vec2<f32>,
-    color: vec4<f32>,
-    frame_x: f32,
-    frame_y: f32,
+struct Clock {
+    // Milliseconds, shared by every gif
+    elapsed: u32,
 }
 
 @group(0) @binding(0) var<uniform> camera: Camera;
 
 @group(1) @binding(0) var texture: texture_2d<f32>;
 @group(1) @binding(1) var texture_sampler: sampler;
-@group(1) @binding(2) var<uniform> frames: Frames; 
+@group(1) @binding(2) var<uniform> frames: Frames;
+// End time of each frame in milliseconds, the last being the loop length
+@group(1) @binding(3) var<storage, read> frame_ends: array<u32>;
 
-@group(2) @binding(0) var<uniform> instance: TextureInstance;
+@group(2) @binding(0) var<uniform> clock: Clock;
 
 //====================================================================
 
@@ -38,27 +37,87 @@ struct VertexIn {
     @location(1) uv: vec2<f32>,
 }
 
+struct InstanceIn {
+    @location(2) pos: vec2<f32>,
+    @location(3) size: vec2<f32>,
+    @location(4) color: vec4<f32>,
+    // Frame to hold on, negative to follow the clock
+    @location(5) frame: i32,
+    // Size of the checkerboard cells behind transparent pixels, 0 for none
+    @location(6) checker: f32,
+    // Brightness, contrast, gamma and invert
+    @location(7) adjust: vec4<f32>,
+    // 0 for all channels, 1-4 for red, green, blue or alpha on its own
+    @location(8) channel: u32,
+}
+
 struct VertexOut {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) uv: vec2<f32>,
     @location(1) color: vec4<f32>,
+    @location(2) @interpolate(flat) frame: vec2<f32>,
+    @location(3) local_pos: vec2<f32>,
+    @location(4) @interpolate(flat) checker: f32,
+    @location(5) @interpolate(flat) adjust: vec4<f32>,
+    @location(6) @interpolate(flat) channel: u32,
 }
 
 //====================================================================
 
+fn current_frame() -> u32 {
+    let count = arrayLength(&frame_ends);
+    let total = frame_ends[count - 1u];
+
+    if count <= 1u || total == 0u {
+        return 0u;
+    }
+
+    let time = clock.elapsed % total;
+
+    // First frame that ends after the current time
+    var low = 0u;
+    var high = count - 1u;
+
+    while low < high {
+        let mid = (low + high) / 2u;
+
+        if frame_ends[mid] <= time {
+            low = mid + 1u;
+        } else {
+            high = mid;
+        }
+    }
+
+    return low;
+}
+
+// adjust_color and checkerboard come from image_color.wgsl
+
 @vertex
-fn vs_main(in: VertexIn) -> VertexOut {
+fn vs_main(in: VertexIn, instance: InstanceIn) -> VertexOut {
     var out: VertexOut;
 
-    var vertex_pos = in.vertex_position 
-        * instance.size 
+    var vertex_pos = in.vertex_position
+        * instance.size
         + instance.pos;
 
-    out.clip_position = camera.projection 
+    out.clip_position = camera.projection
         * vec4<f32>(vertex_pos, 2., 1.);
 
     out.uv = in.uv;
     out.color = instance.color;
+    out.local_pos = in.uv * instance.size;
+    out.checker = instance.checker;
+    out.adjust = instance.adjust;
+    out.channel = instance.channel;
+
+    var frame = current_frame();
+    if instance.frame >= 0 {
+        frame = u32(instance.frame);
+    }
+
+    let frames_per_row = u32(frames.frames_per_row);
+    out.frame = vec2<f32>(f32(frame % frames_per_row), f32(frame / frames_per_row));
 
     return out;
 }
@@ -66,15 +125,19 @@ fn vs_main(in: VertexIn) -> VertexOut {
 @fragment
 fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
 
-    var uv: vec2<f32>;    
-    uv.x = in.uv.x * frames.sample_width + (instance.frame_x * frames.sample_width);
-    uv.y = in.uv.y * frames.sample_height + (instance.frame_y * frames.sample_height);
-    
-    let tex_color = textureSample(texture, texture_sampler, uv);
+    var uv: vec2<f32>;
+    uv.x = in.uv.x * frames.sample_width + (in.frame.x * frames.sample_width);
+    uv.y = in.uv.y * frames.sample_height + (in.frame.y * frames.sample_height);
+
+    var tex_color = textureSample(texture, texture_sampler, uv);
+    tex_color = adjust_color(tex_color, in.adjust, in.channel);
+
+    if in.checker > 0. {
+        let backdrop = checkerboard(in.local_pos, in.checker);
+        tex_color = vec4<f32>(mix(backdrop, tex_color.rgb, tex_color.a), 1.);
+    }
 
     return tex_color * in.color;
 }
 
 //====================================================================
-
-
@@ -0,0 +1,140 @@
+//====================================================================
+
+use half::f16;
+use image::{Rgba32FImage, RgbaImage};
+
+use crate::config::ToneMap;
+
+//====================================================================
+
+/// Linear high bit depth image, uploaded as half floats so values above 1
+/// survive until they're tone mapped in the shader. 16 bit integer images are
+/// already in the display's range and are drawn without tone mapping.
+pub struct HdrTexture {
+    pub _texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    pub scene_referred: bool,
+}
+
+impl HdrTexture {
+    pub fn from_image(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image: &Rgba32FImage,
+        scene_referred: bool,
+        label: Option<&str>,
+    ) -> Self {
+        let (width, height) = image.dimensions();
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba16Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let pixels = image
+            .as_raw()
+            .iter()
+            .map(|value| f16::from_f32(*value).to_bits())
+            .collect::<Vec<_>>();
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::cast_slice(&pixels),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(8 * width),
+                rows_per_image: None,
+            },
+            size,
+        );
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            _texture: texture,
+            view,
+            sampler,
+            scene_referred,
+        }
+    }
+}
+
+//====================================================================
+
+#[inline]
+pub fn srgb_to_linear(value: f32) -> f32 {
+    match value <= 0.04045 {
+        true => value / 12.92,
+        false => ((value + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+#[inline]
+pub fn linear_to_srgb(value: f32) -> f32 {
+    match value <= 0.0031308 {
+        true => value * 12.92,
+        false => 1.055 * value.powf(1. / 2.4) - 0.055,
+    }
+}
+
+/// Same operators as the texture shader
+pub fn tone_map(value: f32, tone_map: ToneMap) -> f32 {
+    let value = value.max(0.);
+
+    match tone_map {
+        ToneMap::Reinhard => value / (1. + value),
+        // Narkowicz's fit of the ACES filmic curve
+        ToneMap::Aces => {
+            ((value * (2.51 * value + 0.03)) / (value * (2.43 * value + 0.59) + 0.14)).clamp(0., 1.)
+        }
+    }
+}
+
+/// Tone map a linear image down to 8 bit sRGB, for the grid's thumbnails
+pub fn tone_map_image(image: &Rgba32FImage, operator: ToneMap) -> RgbaImage {
+    let (width, height) = image.dimensions();
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        let encode = |value: f32| {
+            (linear_to_srgb(tone_map(value, operator)) * 255.)
+                .round()
+                .clamp(0., 255.) as u8
+        };
+
+        image::Rgba([
+            encode(r),
+            encode(g),
+            encode(b),
+            (a.clamp(0., 1.) * 255.).round() as u8,
+        ])
+    })
+}
+
+//====================================================================
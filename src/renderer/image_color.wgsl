@@ -0,0 +1,46 @@
+//====================================================================
+// Shared by the texture and gif shaders, prepended to both
+
+// Display adjustments, worked out on roughly gamma encoded values so steps
+// look even. Isolated channels are shown as greyscale.
+fn adjust_color(color: vec4<f32>, adjust: vec4<f32>, channel: u32) -> vec4<f32> {
+    if channel == 0u && all(adjust == vec4<f32>(0., 1., 1., 0.)) {
+        return color;
+    }
+
+    var rgb = pow(max(color.rgb, vec3<f32>(0.)), vec3<f32>(1. / 2.2));
+    var alpha = color.a;
+
+    switch channel {
+        case 1u: {
+            rgb = vec3<f32>(rgb.r);
+        }
+        case 2u: {
+            rgb = vec3<f32>(rgb.g);
+        }
+        case 3u: {
+            rgb = vec3<f32>(rgb.b);
+        }
+        case 4u: {
+            rgb = vec3<f32>(color.a);
+            alpha = 1.;
+        }
+        default: {}
+    }
+
+    rgb = (rgb - 0.5) * adjust.y + 0.5 + adjust.x;
+    rgb = pow(clamp(rgb, vec3<f32>(0.), vec3<f32>(1.)), vec3<f32>(1. / adjust.z));
+
+    if adjust.w > 0.5 {
+        rgb = 1. - rgb;
+    }
+
+    return vec4<f32>(pow(rgb, vec3<f32>(2.2)), alpha);
+}
+
+// Drawn behind transparent pixels
+fn checkerboard(pos: vec2<f32>, size: f32) -> vec3<f32> {
+    let cell = vec2<i32>(floor(pos / size));
+    return select(vec3<f32>(0.8), vec3<f32>(0.55), ((cell.x + cell.y) & 1) == 1);
+}
+
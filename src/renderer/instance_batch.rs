@@ -0,0 +1,97 @@
+//====================================================================
+
+use std::ops::Range;
+
+use cabat::renderer::render_tools;
+
+//====================================================================
+
+/// Instances for one pass of a textured pipeline. Neighbouring instances that
+/// share a bind group key are drawn together with a single draw call.
+pub struct InstanceBatches<K> {
+    label: &'static str,
+
+    instance_buffer: wgpu::Buffer,
+    instance_count: u32,
+
+    batches: Vec<(K, Range<u32>)>,
+}
+
+impl<K: Copy + PartialEq> InstanceBatches<K> {
+    pub fn new(device: &wgpu::Device, label: &'static str) -> Self {
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: 0,
+            usage: wgpu::BufferUsages::VERTEX,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            label,
+            instance_buffer,
+            instance_count: 0,
+            batches: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.instance_count == 0
+    }
+
+    /// Upload the given instances. Callers wanting fewer draws should sort
+    /// them by key first.
+    pub fn update<T: bytemuck::Pod>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        instances: &[(K, T)],
+    ) {
+        self.batches.clear();
+
+        instances
+            .iter()
+            .enumerate()
+            .for_each(|(index, (key, _))| match self.batches.last_mut() {
+                Some((last, range)) if last == key => range.end += 1,
+                _ => self.batches.push((*key, index as u32..index as u32 + 1)),
+            });
+
+        let raw = instances
+            .iter()
+            .map(|(_, instance)| *instance)
+            .collect::<Vec<_>>();
+
+        render_tools::update_instance_buffer(
+            device,
+            queue,
+            self.label,
+            &mut self.instance_buffer,
+            &mut self.instance_count,
+            &raw,
+        );
+    }
+
+    /// Expects the pipeline, camera and shared vertex/index buffers to already be set
+    pub fn render<'a>(
+        &self,
+        pass: &mut wgpu::RenderPass,
+        index_count: u32,
+        bind_group: impl Fn(K) -> Option<&'a wgpu::BindGroup>,
+    ) {
+        if self.is_empty() {
+            return;
+        }
+
+        pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+
+        self.batches.iter().for_each(|(key, range)| {
+            if let Some(bind_group) = bind_group(*key) {
+                pass.set_bind_group(1, bind_group, &[]);
+                pass.draw_indexed(0..index_count, 0, range.clone());
+            }
+        });
+    }
+}
+
+//====================================================================
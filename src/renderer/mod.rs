@@ -1,22 +1,32 @@
 //====================================================================
 
 use cabat::{
-    common::WindowResizeEvent,
+    common::{WindowResizeEvent, WindowSize},
     renderer::{Device, RenderPass, SurfaceConfig},
     shipyard_tools::{prelude::*, UniqueTools},
 };
 use camera::{sys_resize_camera, sys_setup_camera, sys_update_camera, MainCamera, UiCamera};
 use circle_pipeline::{sys_update_circle_pipeline, CirclePipeline};
-use gif2d_pipeline::Gif2dPipeline;
-use shipyard::{AllStoragesView, IntoIter, IntoWorkload, View};
-use texture2d_pipeline::Texture2dPipeline;
+use gif2d_pipeline::{sys_update_gif_pipeline, Gif2dPipeline};
+use rect_pipeline::{sys_update_rect_pipeline, RectPipeline, RectShape, UiRect};
+use shipyard::{AllStoragesView, EntitiesViewMut, EntityId, Get, IntoWorkload, Unique, ViewMut};
+use texture2d_pipeline::{sys_update_texture_pipeline, Texture2dPipeline};
 
-use crate::images::{GifImage, ImageShown, StandardImage};
+use crate::{
+    config::{BackgroundStyle, Settings, SettingsFile},
+    images::Pos,
+    keybinds::{Action, ActionInput},
+    layout::LayoutManager,
+};
 
+pub mod atlas;
 pub mod camera;
 pub mod circle_pipeline;
 pub mod gif;
 pub mod gif2d_pipeline;
+pub mod hdr;
+pub mod instance_batch;
+pub mod rect_pipeline;
 pub mod texture2d_pipeline;
 
 //====================================================================
@@ -30,13 +40,31 @@ impl Plugin for CustomRendererPlugin {
                 Stages::Setup,
                 (sys_setup_camera, sys_setup_pipelines).into_sequential_workload(),
             )
+            .add_workload(Stages::Setup, sys_setup_background)
+            .add_workload(
+                Stages::Update,
+                (sys_change_background, sys_update_background).into_sequential_workload(),
+            )
             .add_workload_last(
                 Stages::Update,
-                (sys_update_circle_pipeline, sys_update_camera),
+                (
+                    sys_update_circle_pipeline,
+                    sys_update_rect_pipeline,
+                    sys_update_texture_pipeline,
+                    sys_update_gif_pipeline,
+                    sys_update_camera,
+                ),
             )
             .add_workload(
                 Stages::Render,
-                (sys_render_circles, sys_render_textures, sys_render_gifs).into_workload(),
+                (
+                    sys_render_ui_rects,
+                    sys_render_circles,
+                    sys_render_textures,
+                    sys_render_gifs,
+                    sys_render_rects,
+                )
+                    .into_workload(),
             )
             .add_event::<WindowResizeEvent>((sys_resize_camera).into_workload());
     }
@@ -65,11 +93,93 @@ fn sys_setup_pipelines(
             device.inner(),
             config.inner(),
             camera.camera.bind_group_layout(),
+        ))
+        .insert(RectPipeline::new(
+            device.inner(),
+            config.inner(),
+            camera.camera.bind_group_layout(),
         ));
 }
 
 //====================================================================
 
+#[derive(Unique)]
+pub struct Background {
+    id: EntityId,
+}
+
+fn sys_setup_background(
+    all_storages: AllStoragesView,
+    mut entities: EntitiesViewMut,
+
+    mut vm_pos: ViewMut<Pos>,
+    mut vm_rect: ViewMut<RectShape>,
+    mut vm_ui: ViewMut<UiRect>,
+) {
+    // Plate behind everything else, covering the window
+    let id = entities.add_entity(
+        (&mut vm_pos, &mut vm_rect, &mut vm_ui),
+        (
+            Pos { x: 0., y: 0. },
+            RectShape::outline(0., 0., 0., [0., 0., 0., 0.]).with_depth(2.5),
+            UiRect,
+        ),
+    );
+
+    all_storages.add_unique(Background { id });
+}
+
+fn sys_change_background(
+    actions: ActionInput,
+    mut settings: ResMut<Settings>,
+    mut settings_file: ResMut<SettingsFile>,
+) {
+    let appearance = &mut settings.appearance;
+
+    if actions.just_pressed(Action::CycleBackground) {
+        appearance.background_style = appearance.background_style.next();
+        log::info!("Background: {:?}", appearance.background_style);
+    } else if actions.just_pressed(Action::ToggleTransparencyChecker) {
+        appearance.transparency_checker = !appearance.transparency_checker;
+    } else {
+        return;
+    }
+
+    settings_file.request_save();
+}
+
+fn sys_update_background(
+    window_size: Res<WindowSize>,
+    settings: Res<Settings>,
+    background: Res<Background>,
+
+    mut vm_rect: ViewMut<RectShape>,
+) {
+    let mut rect = (&mut vm_rect).get(background.id).unwrap();
+
+    rect.width = window_size.width_f32();
+    rect.height = window_size.height_f32();
+
+    (rect.color, rect.checker) = match settings.appearance.background_style {
+        BackgroundStyle::Dark => ([0.1, 0.1, 0.1, 1.], 0.),
+        BackgroundStyle::Light => ([0.85, 0.85, 0.85, 1.], 0.),
+        BackgroundStyle::Checker => ([0.4, 0.4, 0.4, 1.], 16.),
+        BackgroundStyle::Custom => (settings.appearance.background, 0.),
+    };
+}
+
+//====================================================================
+
+// Drawn first so the background plate sits behind everything, other ui rects
+// still end up in front through the depth buffer
+fn sys_render_ui_rects(
+    mut pass: ResMut<RenderPass>,
+    rect_pipeline: Res<RectPipeline>,
+    ui_camera: Res<UiCamera>,
+) {
+    rect_pipeline.render_ui(pass.pass(), ui_camera.camera.bind_group());
+}
+
 fn sys_render_circles(
     mut pass: ResMut<RenderPass>,
     circle_pipeline: Res<CirclePipeline>,
@@ -84,34 +194,22 @@ fn sys_render_textures(
 
     main_camera: Res<MainCamera>,
     ui_camera: Res<UiCamera>,
-
-    v_images: View<StandardImage>,
-    v_shown: View<ImageShown>,
+    window_size: Res<WindowSize>,
+    layout: Res<LayoutManager>,
 ) {
-    let images = (&v_images, !&v_shown)
-        .iter()
-        .map(|(image, _)| &image.instance);
-
     texture_pipeline.render(
         pass.pass(),
         main_camera.camera.bind_group(),
-        images.into_iter(),
         // Some(viewport.inner()), // BUG - fix viewport not working with world space
         None,
     );
 
-    if !v_shown.is_empty() {
-        let images = (&v_images, &v_shown)
-            .iter()
-            .map(|(image, _)| &image.instance);
-
-        texture_pipeline.render(
-            pass.pass(),
-            ui_camera.camera.bind_group(),
-            images.into_iter(),
-            // Some(viewport.inner()), // BUG - fix viewport not working with world space
-            None,
-        );
+    if texture_pipeline.has_viewer_images() {
+        if set_viewer_scissor(pass.pass(), &window_size, &layout) {
+            texture_pipeline.render_viewer(pass.pass(), ui_camera.camera.bind_group());
+
+            reset_scissor(pass.pass(), &window_size);
+        }
     }
 }
 
@@ -121,29 +219,61 @@ fn sys_render_gifs(
 
     main_camera: Res<MainCamera>,
     ui_camera: Res<UiCamera>,
+    window_size: Res<WindowSize>,
+    layout: Res<LayoutManager>,
+) {
+    gif_pipeline.render(pass.pass(), main_camera.camera.bind_group());
+
+    if gif_pipeline.has_viewer_images() {
+        if set_viewer_scissor(pass.pass(), &window_size, &layout) {
+            gif_pipeline.render_viewer(pass.pass(), ui_camera.camera.bind_group());
+
+            reset_scissor(pass.pass(), &window_size);
+        }
+    }
+}
 
-    v_gifs: View<GifImage>,
-    v_shown: View<ImageShown>,
+// Selection outlines and the rubber band live in grid space
+fn sys_render_rects(
+    mut pass: ResMut<RenderPass>,
+    rect_pipeline: Res<RectPipeline>,
+    main_camera: Res<MainCamera>,
+    layout: Res<LayoutManager>,
 ) {
-    let images = (&v_gifs, !&v_shown)
-        .iter()
-        .map(|(image, _)| &image.instance);
+    if layout.fullscreen() {
+        return;
+    }
 
-    gif_pipeline.render(
-        pass.pass(),
-        main_camera.camera.bind_group(),
-        images.into_iter(),
-    );
+    rect_pipeline.render(pass.pass(), main_camera.camera.bind_group());
+}
+
+//====================================================================
+
+// Keep zoomed in or panned images from spilling over the grid. Returns false
+// when there's nothing to draw into, such as a minimized window or collapsed pane.
+fn set_viewer_scissor(
+    pass: &mut wgpu::RenderPass,
+    window_size: &WindowSize,
+    layout: &LayoutManager,
+) -> bool {
+    let (pane_pos, pane_size) = layout.viewer_pane(window_size);
 
-    if !v_shown.is_empty() {
-        let images = (&v_gifs, &v_shown).iter().map(|(image, _)| &image.instance);
+    let x = (window_size.width_f32() / 2. + pane_pos.x - pane_size.x / 2.).max(0.) as u32;
+    let x = x.min(window_size.width());
+    let width = (pane_size.x.max(0.) as u32).min(window_size.width() - x);
+    let height = window_size.height();
 
-        gif_pipeline.render(
-            pass.pass(),
-            &ui_camera.camera.bind_group(),
-            images.into_iter(),
-        );
+    if width == 0 || height == 0 {
+        return false;
     }
+
+    pass.set_scissor_rect(x, 0, width, height);
+    true
+}
+
+#[inline]
+fn reset_scissor(pass: &mut wgpu::RenderPass, window_size: &WindowSize) {
+    pass.set_scissor_rect(0, 0, window_size.width(), window_size.height());
 }
 
 //====================================================================
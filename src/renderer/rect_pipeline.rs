@@ -0,0 +1,278 @@
+//====================================================================
+
+use cabat::{
+    renderer::{
+        render_tools::{self, RenderPipelineDescriptor},
+        Device, Queue, Vertex,
+    },
+    shipyard_tools::{Res, ResMut},
+};
+use shipyard::{Component, IntoIter, Unique, View};
+use wgpu::util::DeviceExt;
+
+use crate::images::Pos;
+
+use super::circle_pipeline::{RawVertex, INDICES, VERTICES};
+
+//====================================================================
+
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, Debug)]
+pub struct RawRectInstance {
+    pub pos: [f32; 2],
+    pub size: [f32; 2],
+    pub color: [f32; 4],
+    pub border_color: [f32; 4],
+    pub border_width: f32,
+    pub depth: f32,
+    pub corner_radius: f32,
+    pub checker: f32,
+}
+
+impl Vertex for RawRectInstance {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 8] = wgpu::vertex_attr_array![
+            1 => Float32x2, 2 => Float32x2, 3 => Float32x4, 4 => Float32x4, 5 => Float32, 6 => Float32,
+            7 => Float32, 8 => Float32,
+        ];
+
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<RawRectInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &VERTEX_ATTRIBUTES,
+        }
+    }
+}
+
+//====================================================================
+
+#[derive(Unique)]
+pub struct RectPipeline {
+    pipeline: wgpu::RenderPipeline,
+
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    index_count: u32,
+
+    instance_buffer: wgpu::Buffer,
+    instance_count: u32,
+
+    ui_instance_buffer: wgpu::Buffer,
+    ui_instance_count: u32,
+}
+
+impl RectPipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let pipeline = render_tools::create_pipeline(
+            device,
+            config,
+            "Rect Pipeline",
+            &[&camera_bind_group_layout],
+            &[RawVertex::desc(), RawRectInstance::desc()],
+            include_str!("rect_shader.wgsl").into(),
+            RenderPipelineDescriptor::default()
+                .with_depth_stencil()
+                .with_backface_culling(),
+        );
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Rect Pipeline Vertex Buffer"),
+            contents: bytemuck::cast_slice(&VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Rect Pipeline Index Buffer"),
+            contents: bytemuck::cast_slice(&INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let index_count = INDICES.len() as u32;
+
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Rect Pipeline Instance Buffer"),
+            size: 0,
+            usage: wgpu::BufferUsages::VERTEX,
+            mapped_at_creation: false,
+        });
+        let instance_count = 0 as u32;
+
+        let ui_instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Rect Pipeline Ui Instance Buffer"),
+            size: 0,
+            usage: wgpu::BufferUsages::VERTEX,
+            mapped_at_creation: false,
+        });
+        let ui_instance_count = 0 as u32;
+
+        Self {
+            pipeline,
+            vertex_buffer,
+            index_buffer,
+            index_count,
+            instance_buffer,
+            instance_count,
+            ui_instance_buffer,
+            ui_instance_count,
+        }
+    }
+
+    pub fn render(&self, pass: &mut wgpu::RenderPass, camera_bind_group: &wgpu::BindGroup) {
+        self.draw(
+            pass,
+            camera_bind_group,
+            &self.instance_buffer,
+            self.instance_count,
+        );
+    }
+
+    /// Draw rects marked with [`UiRect`], which are positioned in screen space
+    pub fn render_ui(&self, pass: &mut wgpu::RenderPass, camera_bind_group: &wgpu::BindGroup) {
+        self.draw(
+            pass,
+            camera_bind_group,
+            &self.ui_instance_buffer,
+            self.ui_instance_count,
+        );
+    }
+
+    fn draw(
+        &self,
+        pass: &mut wgpu::RenderPass,
+        camera_bind_group: &wgpu::BindGroup,
+        instance_buffer: &wgpu::Buffer,
+        instance_count: u32,
+    ) {
+        if instance_count == 0 {
+            return;
+        }
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, camera_bind_group, &[]);
+
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        pass.set_vertex_buffer(1, instance_buffer.slice(..));
+
+        pass.draw_indexed(0..self.index_count, 0, 0..instance_count);
+    }
+
+    fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        instances: &[RawRectInstance],
+        ui_instances: &[RawRectInstance],
+    ) {
+        render_tools::update_instance_buffer(
+            device,
+            queue,
+            "Rect Pipeline Instance Buffer",
+            &mut self.instance_buffer,
+            &mut self.instance_count,
+            instances,
+        );
+
+        render_tools::update_instance_buffer(
+            device,
+            queue,
+            "Rect Pipeline Ui Instance Buffer",
+            &mut self.ui_instance_buffer,
+            &mut self.ui_instance_count,
+            ui_instances,
+        );
+    }
+}
+
+//====================================================================
+
+#[derive(Component)]
+pub struct RectShape {
+    pub width: f32,
+    pub height: f32,
+    pub color: [f32; 4],
+    pub border_color: [f32; 4],
+    pub border_width: f32,
+    pub depth: f32,
+    pub corner_radius: f32,
+    // Size of checkerboard cells, every other one drawn darker. 0 for a solid fill.
+    pub checker: f32,
+}
+
+impl RectShape {
+    pub fn outline(width: f32, height: f32, border_width: f32, border_color: [f32; 4]) -> Self {
+        Self {
+            width,
+            height,
+            color: [0., 0., 0., 0.],
+            border_color,
+            border_width,
+            depth: 1.,
+            corner_radius: 0.,
+            checker: 0.,
+        }
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_depth(mut self, depth: f32) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn with_corner_radius(mut self, corner_radius: f32) -> Self {
+        self.corner_radius = corner_radius;
+        self
+    }
+}
+
+#[derive(Component)]
+pub struct UiRect;
+
+pub(super) fn sys_update_rect_pipeline(
+    device: Res<Device>,
+    queue: Res<Queue>,
+    mut pipeline: ResMut<RectPipeline>,
+
+    v_rect: View<RectShape>,
+    v_pos: View<Pos>,
+    v_ui: View<UiRect>,
+) {
+    let raw = |rect: &RectShape, pos: &Pos| RawRectInstance {
+        pos: [pos.x, pos.y],
+        size: [rect.width, rect.height],
+        color: rect.color,
+        border_color: rect.border_color,
+        border_width: rect.border_width,
+        depth: rect.depth,
+        corner_radius: rect.corner_radius,
+        checker: rect.checker,
+    };
+
+    // Back to front so translucent rects blend over the ones behind them
+    let sort = |instances: &mut Vec<RawRectInstance>| {
+        instances.sort_by(|a, b| b.depth.total_cmp(&a.depth));
+    };
+
+    let mut instances = (&v_rect, &v_pos, !&v_ui)
+        .iter()
+        .map(|(rect, pos, _)| raw(rect, pos))
+        .collect::<Vec<_>>();
+    sort(&mut instances);
+
+    let mut ui_instances = (&v_rect, &v_pos, &v_ui)
+        .iter()
+        .map(|(rect, pos, _)| raw(rect, pos))
+        .collect::<Vec<_>>();
+    sort(&mut ui_instances);
+
+    pipeline.update(device.inner(), queue.inner(), &instances, &ui_instances);
+}
+
+//====================================================================
@@ -0,0 +1,94 @@
+//====================================================================
+// Uniforms
+
+struct Camera {
+    projection: mat4x4<f32>,
+    position: vec3<f32>,
+}
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+//====================================================================
+
+struct VertexIn {
+    // Vertex
+    @location(0) vertex_pos: vec2<f32>,
+    // Instance
+    @location(1) pos: vec2<f32>,
+    @location(2) size: vec2<f32>,
+    @location(3) color: vec4<f32>,
+    @location(4) border_color: vec4<f32>,
+    @location(5) border_width: f32,
+    @location(6) depth: f32,
+    @location(7) corner_radius: f32,
+    @location(8) checker: f32,
+}
+
+struct VertexOut {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) local_pos: vec2<f32>,
+    @location(1) half_size: vec2<f32>,
+    @location(2) border_width: f32,
+    @location(3) color: vec4<f32>,
+    @location(4) border_color: vec4<f32>,
+    @location(5) corner_radius: f32,
+    @location(6) checker: f32,
+}
+
+//====================================================================
+
+@vertex
+fn vs_main(in: VertexIn) -> VertexOut {
+    var out: VertexOut;
+
+    var local_pos = in.vertex_pos * in.size;
+    var vertex_pos = local_pos + in.pos;
+
+    out.clip_position = camera.projection
+        * vec4<f32>(vertex_pos, in.depth, 1.);
+
+    out.local_pos = local_pos;
+    out.half_size = in.size / 2.;
+    out.border_width = in.border_width;
+    out.corner_radius = min(in.corner_radius, min(out.half_size.x, out.half_size.y));
+
+    out.color = in.color;
+    out.border_color = in.border_color;
+    out.checker = in.checker;
+
+    return out;
+}
+
+// Signed distance from the edge of a rounded box, negative inside
+fn rounded_box(pos: vec2<f32>, half_size: vec2<f32>, radius: f32) -> f32 {
+    let q = abs(pos) - half_size + vec2<f32>(radius);
+    return length(max(q, vec2<f32>(0.))) + min(max(q.x, q.y), 0.) - radius;
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    let distance = rounded_box(in.local_pos, in.half_size, in.corner_radius);
+
+    // Blend a pixel wide band at the outer and inner edges of the border
+    let outer = clamp(0.5 - distance, 0., 1.);
+    let inner = clamp(0.5 - (distance + in.border_width), 0., 1.);
+
+    var color = in.color;
+    if in.checker > 0. {
+        let cell = vec2<i32>(floor(in.local_pos / in.checker));
+        if ((cell.x + cell.y) & 1) == 1 {
+            color = vec4<f32>(color.rgb * 0.7, color.a);
+        }
+    }
+    if in.border_width > 0. {
+        color = mix(in.border_color, color, inner);
+    }
+    color.w *= outer;
+
+    if color.w <= 0. {
+        discard;
+    }
+    return color;
+}
+
+//====================================================================
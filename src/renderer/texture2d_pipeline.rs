@@ -1,16 +1,34 @@
 //====================================================================
 
-use cabat::renderer::{
-    render_tools,
-    shared::{
-        TextureRectVertex, TEXTURE_RECT_INDEX_COUNT, TEXTURE_RECT_INDICES, TEXTURE_RECT_VERTICES,
+use ahash::{AHashMap, AHashSet};
+use cabat::{
+    renderer::{
+        render_tools,
+        shared::{
+            TextureRectVertex, TEXTURE_RECT_INDEX_COUNT, TEXTURE_RECT_INDICES,
+            TEXTURE_RECT_VERTICES,
+        },
+        texture, Device, Queue, Vertex,
     },
-    texture, Vertex,
+    shipyard_tools::{Res, ResMut},
 };
-use shipyard::Unique;
-use wgpu::util::DeviceExt;
+use image::RgbaImage;
+use shipyard::{IntoIter, Unique, View};
 
-use crate::tools::Rect;
+use super::{
+    atlas::{Atlas, THUMBNAIL_SIZE},
+    hdr::HdrTexture,
+    instance_batch::InstanceBatches,
+};
+use crate::{
+    adjustments::{Adjustments, ImageAdjustments},
+    config::{Settings, ToneMap},
+    exposure::Exposure,
+    images::{Color, ImageDirty, ImageIndex, ImageSize, ImageVisible, Pos, StandardImage},
+    layout::LayoutManager,
+    storage::TextureID,
+    tools::Rect,
+};
 
 //====================================================================
 
@@ -20,50 +38,62 @@ pub struct Texture2dInstanceRaw {
     pub pos: [f32; 2],
     pub size: [f32; 2],
     pub color: [f32; 4],
+    // Offset and scale of the part of the texture to draw
+    pub uv: [f32; 4],
+    // Multiplier applied before tone mapping
+    pub exposure: f32,
+    // 0 for standard images, otherwise the operator for high dynamic range ones
+    pub tone_map: u32,
+    // Size of the checkerboard cells drawn behind transparent pixels, 0 for none
+    pub checker: f32,
+    // Brightness, contrast, gamma and invert
+    pub adjust: [f32; 4],
+    // 0 for all channels, otherwise red, green, blue or alpha on its own
+    pub channel: u32,
 }
 
-pub struct Texture2dInstance {
-    bind_group: wgpu::BindGroup,
-    buffer: wgpu::Buffer,
+impl Vertex for Texture2dInstanceRaw {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 9] = wgpu::vertex_attr_array![
+            2 => Float32x2, 3 => Float32x2, 4 => Float32x4, 5 => Float32x4, 6 => Float32, 7 => Uint32,
+            8 => Float32, 9 => Float32x4, 10 => Uint32,
+        ];
 
-    texture_bind_group: wgpu::BindGroup,
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Texture2dInstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &VERTEX_ATTRIBUTES,
+        }
+    }
 }
 
-impl Texture2dInstance {
-    pub fn new(
-        device: &wgpu::Device,
-        pipeline: &Texture2dPipeline,
-        data: Texture2dInstanceRaw,
-        texture: &texture::RawTexture,
-    ) -> Self {
-        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Texture Instance"),
-            contents: bytemuck::cast_slice(&[data]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
+const FULL_UV: [f32; 4] = [0., 0., 1., 1.];
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &pipeline.texture_instance_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::Buffer(buffer.as_entire_buffer_binding()),
-            }],
-        });
+pub const CHECKER_SIZE: f32 = 8.;
 
-        let texture_bind_group = pipeline.load_texture(&device, texture);
-
-        Self {
-            bind_group,
-            buffer,
-            texture_bind_group,
-        }
+const fn tone_map_id(tone_map: ToneMap) -> u32 {
+    match tone_map {
+        ToneMap::Reinhard => 1,
+        ToneMap::Aces => 2,
     }
+}
 
-    #[inline]
-    pub fn update(&self, queue: &wgpu::Queue, data: Texture2dInstanceRaw) {
-        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[data]));
-    }
+/// What an instance samples from. Grid thumbnails come from the atlas while
+/// anything larger uses the image's own texture.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum TextureSource {
+    Atlas(usize),
+    Texture(TextureID),
+}
+
+/// Everything besides the images themselves that goes into their instances
+#[derive(Clone, Copy, PartialEq)]
+struct InstanceParams {
+    fullscreen: bool,
+    use_atlas: bool,
+    checker: f32,
+    tone_map: u32,
+    exposure: f32,
 }
 
 //====================================================================
@@ -72,11 +102,23 @@ impl Texture2dInstance {
 pub struct Texture2dPipeline {
     pipeline: wgpu::RenderPipeline,
     texture_bind_group_layout: wgpu::BindGroupLayout,
-    pub texture_instance_bind_group_layout: wgpu::BindGroupLayout,
 
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     index_count: u32,
+
+    textures: AHashMap<TextureID, wgpu::BindGroup>,
+    // Textures holding linear values that need tone mapping
+    hdr_textures: AHashSet<TextureID>,
+    atlas: Atlas,
+
+    grid: InstanceBatches<TextureSource>,
+    viewer: InstanceBatches<TextureSource>,
+
+    // Instances are only rebuilt when images are dirty, textures change or
+    // the params they were built with change
+    rebuild: bool,
+    params: Option<InstanceParams>,
 }
 
 impl Texture2dPipeline {
@@ -97,27 +139,27 @@ impl Texture2dPipeline {
                 ],
             });
 
-        let texture_instance_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Texture Instance Bind Group Layout"),
-                entries: &[render_tools::bgl_uniform_entry(
-                    0,
-                    wgpu::ShaderStages::VERTEX_FRAGMENT,
-                )],
-            });
-
+        // Images are uploaded with straight (not premultiplied) alpha, the
+        // shader only ever scales the alpha channel when fading
         let pipeline = render_tools::create_pipeline(
             &device,
             &config,
             "Texture Pipeline",
-            &[
-                camera_bind_group_layout,
-                &texture_bind_group_layout,
-                &texture_instance_bind_group_layout,
-            ],
-            &[TextureRectVertex::desc()],
-            include_str!("texture_shader.wgsl"),
-            render_tools::RenderPipelineDescriptor::default().with_depth_stencil(),
+            &[camera_bind_group_layout, &texture_bind_group_layout],
+            &[TextureRectVertex::desc(), Texture2dInstanceRaw::desc()],
+            concat!(
+                include_str!("image_color.wgsl"),
+                include_str!("texture_shader.wgsl")
+            ),
+            render_tools::RenderPipelineDescriptor {
+                fragment_targets: Some(&[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::all(),
+                })]),
+                ..Default::default()
+            }
+            .with_depth_stencil(),
         );
 
         let vertex_buffer =
@@ -130,39 +172,116 @@ impl Texture2dPipeline {
         Self {
             pipeline,
             texture_bind_group_layout,
-            texture_instance_bind_group_layout,
             vertex_buffer,
             index_buffer,
             index_count,
+            textures: AHashMap::new(),
+            hdr_textures: AHashSet::new(),
+            atlas: Atlas::new(device),
+            grid: InstanceBatches::new(device, "Texture Pipeline Grid Instance Buffer"),
+            viewer: InstanceBatches::new(device, "Texture Pipeline Viewer Instance Buffer"),
+            rebuild: false,
+            params: None,
         }
     }
 
-    pub fn load_texture(
-        &self,
+    pub fn add_texture(
+        &mut self,
         device: &wgpu::Device,
+        id: TextureID,
         data: &texture::RawTexture,
-    ) -> wgpu::BindGroup {
-        device.create_bind_group(&wgpu::BindGroupDescriptor {
+    ) {
+        self.add_bind_group(device, id, &data.view, &data.sampler);
+    }
+
+    pub fn add_hdr_texture(&mut self, device: &wgpu::Device, id: TextureID, data: &HdrTexture) {
+        self.add_bind_group(device, id, &data.view, &data.sampler);
+
+        if data.scene_referred {
+            self.hdr_textures.insert(id);
+        }
+    }
+
+    fn add_bind_group(
+        &mut self,
+        device: &wgpu::Device,
+        id: TextureID,
+        view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+    ) {
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("TextureBindGroup"),
             layout: &self.texture_bind_group_layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&data.view),
+                    resource: wgpu::BindingResource::TextureView(view),
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&data.sampler),
+                    resource: wgpu::BindingResource::Sampler(sampler),
                 },
             ],
-        })
+        });
+
+        self.textures.insert(id, bind_group);
+        self.rebuild = true;
+    }
+
+    pub fn add_thumbnail(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        id: TextureID,
+        thumbnail: &RgbaImage,
+    ) {
+        let region = self.atlas.insert(
+            device,
+            queue,
+            &self.texture_bind_group_layout,
+            id,
+            thumbnail,
+        );
+
+        if region.is_none() {
+            log::warn!("Failed to fit thumbnail {} in the atlas", id);
+        }
+
+        self.rebuild = true;
+    }
+
+    /// Drop a texture's bind group and free its thumbnail's space in the atlas
+    pub fn remove_texture(&mut self, id: TextureID) {
+        self.textures.remove(&id);
+        self.hdr_textures.remove(&id);
+        self.atlas.remove(id);
+        self.rebuild = true;
     }
 
-    pub fn render<'a, I: Iterator<Item = &'a Texture2dInstance>>(
+    fn bind_group(&self, source: TextureSource) -> Option<&wgpu::BindGroup> {
+        match source {
+            TextureSource::Atlas(page) => self.atlas.bind_group(page),
+            TextureSource::Texture(id) => self.textures.get(&id),
+        }
+    }
+
+    #[inline]
+    pub fn has_viewer_images(&self) -> bool {
+        !self.viewer.is_empty()
+    }
+
+    fn prepare(&self, pass: &mut wgpu::RenderPass, camera_bind_goup: &wgpu::BindGroup) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+
+        pass.set_bind_group(0, camera_bind_goup, &[]);
+    }
+
+    pub fn render(
         &self,
         pass: &mut wgpu::RenderPass,
         camera_bind_goup: &wgpu::BindGroup,
-        to_render: I,
         viewport: Option<&Rect>,
     ) {
         if let Some(viewport) = viewport {
@@ -176,19 +295,128 @@ impl Texture2dPipeline {
             );
         }
 
-        pass.set_pipeline(&self.pipeline);
-        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        self.prepare(pass, camera_bind_goup);
+        self.grid
+            .render(pass, self.index_count, |source| self.bind_group(source));
+    }
 
-        pass.set_bind_group(0, camera_bind_goup, &[]);
+    pub fn render_viewer(&self, pass: &mut wgpu::RenderPass, camera_bind_goup: &wgpu::BindGroup) {
+        self.prepare(pass, camera_bind_goup);
+        self.viewer
+            .render(pass, self.index_count, |source| self.bind_group(source));
+    }
+}
 
-        to_render.for_each(|to_render| {
-            pass.set_bind_group(1, &to_render.texture_bind_group, &[]);
-            pass.set_bind_group(2, &to_render.bind_group, &[]);
+//====================================================================
 
-            pass.draw_indexed(0..self.index_count, 0, 0..1);
-        });
+pub(super) fn sys_update_texture_pipeline(
+    device: Res<Device>,
+    queue: Res<Queue>,
+    layout: Res<LayoutManager>,
+    settings: Res<Settings>,
+    exposure: Res<Exposure>,
+    adjustments: Res<Adjustments>,
+    mut pipeline: ResMut<Texture2dPipeline>,
+
+    v_image: View<StandardImage>,
+    v_pos: View<Pos>,
+    v_size: View<ImageSize>,
+    v_color: View<Color>,
+    v_index: View<ImageIndex>,
+    v_visible: View<ImageVisible>,
+    v_dirty: View<ImageDirty>,
+) {
+    let params = InstanceParams {
+        fullscreen: layout.fullscreen(),
+        // Thumbnails only hold up while tiles aren't drawn larger than them
+        use_atlas: layout.tile_size().max_element() <= THUMBNAIL_SIZE as f32,
+        checker: match settings.appearance.transparency_checker {
+            true => CHECKER_SIZE,
+            false => 0.,
+        },
+        tone_map: tone_map_id(settings.appearance.tone_map),
+        exposure: exposure.scale(),
+    };
+
+    if !pipeline.rebuild && v_dirty.is_empty() && pipeline.params == Some(params) {
+        return;
     }
+
+    pipeline.rebuild = false;
+    pipeline.params = Some(params);
+
+    let checker = params.checker;
+
+    let raw = |pos: &Pos,
+               size: &ImageSize,
+               color: &Color,
+               uv: [f32; 4],
+               tone: (f32, u32),
+               adjustments: ImageAdjustments| {
+        let (adjust, channel) = adjustments.raw();
+
+        Texture2dInstanceRaw {
+            pos: pos.to_array(),
+            size: size.to_array(),
+            color: color.to_array(),
+            uv,
+            exposure: tone.0,
+            tone_map: tone.1,
+            checker,
+            adjust,
+            channel,
+        }
+    };
+
+    // Exposure is only adjusted in the viewer. Atlas thumbnails were already tone mapped when loaded.
+    let tone = |source: TextureSource, exposure: f32| match source {
+        TextureSource::Texture(id) if pipeline.hdr_textures.contains(&id) => {
+            (exposure, params.tone_map)
+        }
+        _ => (1., 0),
+    };
+
+    let grid_source = |id: TextureID| match pipeline.atlas.region(id) {
+        Some(region) if params.use_atlas => (TextureSource::Atlas(region.page), region.uv()),
+        _ => (TextureSource::Texture(id), FULL_UV),
+    };
+
+    // Grid order doesn't matter, so tiles sharing a texture are drawn together
+    let mut grid = match params.fullscreen {
+        true => Vec::new(),
+        false => (&v_image, &v_pos, &v_size, &v_color, &v_index, &v_visible)
+            .iter()
+            .map(|(image, pos, size, color, _, _)| {
+                let (source, uv) = grid_source(image.id);
+                let tone = tone(source, 1.);
+                (
+                    source,
+                    raw(pos, size, color, uv, tone, ImageAdjustments::default()),
+                )
+            })
+            .collect::<Vec<_>>(),
+    };
+    grid.sort_by_key(|(source, _)| *source);
+
+    // Anything outside of the grid is shown (or fading out) in the viewer, the
+    // only place display adjustments apply
+    let viewer = (&v_image, &v_pos, &v_size, &v_color, !&v_index)
+        .iter()
+        .map(|(image, pos, size, color, _)| {
+            let source = TextureSource::Texture(image.id);
+            let tone = tone(source, params.exposure);
+            (
+                source,
+                raw(pos, size, color, FULL_UV, tone, adjustments.get(image.id)),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let pipeline = &mut *pipeline;
+    pipeline.grid.update(device.inner(), queue.inner(), &grid);
+    pipeline
+        .viewer
+        .update(device.inner(), queue.inner(), &viewer);
 }
 
 //====================================================================
@@ -6,19 +6,11 @@ struct Camera {
     position: vec3<f32>,
 }
 
-struct TextureInstance {
-    pos: vec2<f32>,
-    size: vec2<f32>,
-    color: vec4<f32>,
-}
-
 @group(0) @binding(0) var<uniform> camera: Camera;
 
 @group(1) @binding(0) var texture: texture_2d<f32>;
 @group(1) @binding(1) var texture_sampler: sampler;
 
-@group(2) @binding(0) var<uniform> instance: TextureInstance; 
-
 //====================================================================
 
 struct VertexIn {
@@ -27,37 +19,100 @@ struct VertexIn {
     @location(1) uv: vec2<f32>,
 }
 
+struct InstanceIn {
+    @location(2) pos: vec2<f32>,
+    @location(3) size: vec2<f32>,
+    @location(4) color: vec4<f32>,
+    // Offset (xy) and scale (zw) into the texture, for atlas regions
+    @location(5) uv: vec4<f32>,
+    @location(6) exposure: f32,
+    // 0 for standard images, 1 Reinhard, 2 ACES
+    @location(7) tone_map: u32,
+    // Size of the checkerboard cells behind transparent pixels, 0 for none
+    @location(8) checker: f32,
+    // Brightness, contrast, gamma and invert
+    @location(9) adjust: vec4<f32>,
+    // 0 for all channels, 1-4 for red, green, blue or alpha on its own
+    @location(10) channel: u32,
+}
+
 struct VertexOut {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) uv: vec2<f32>,
     @location(1) color: vec4<f32>,
+    @location(2) @interpolate(flat) exposure: f32,
+    @location(3) @interpolate(flat) tone_map: u32,
+    // Position within the image, so the checkerboard moves with it
+    @location(4) local_pos: vec2<f32>,
+    @location(5) @interpolate(flat) checker: f32,
+    @location(6) @interpolate(flat) adjust: vec4<f32>,
+    @location(7) @interpolate(flat) channel: u32,
+}
+
+//====================================================================
+
+fn reinhard(color: vec3<f32>) -> vec3<f32> {
+    return color / (1. + color);
 }
 
+// Narkowicz's fit of the ACES filmic curve
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    let mapped = (color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14);
+    return clamp(mapped, vec3<f32>(0.), vec3<f32>(1.));
+}
+
+// adjust_color and checkerboard come from image_color.wgsl
+
 //====================================================================
 
 @vertex
-fn vs_main(in: VertexIn) -> VertexOut {
+fn vs_main(in: VertexIn, instance: InstanceIn) -> VertexOut {
     var out: VertexOut;
 
-    var vertex_pos = in.vertex_position 
-        * instance.size 
+    var vertex_pos = in.vertex_position
+        * instance.size
         + instance.pos;
 
-    out.clip_position = camera.projection 
+    out.clip_position = camera.projection
         * vec4<f32>(vertex_pos, 2., 1.);
 
-    out.uv = in.uv;
+    out.uv = instance.uv.xy + in.uv * instance.uv.zw;
     out.color = instance.color;
+    out.exposure = instance.exposure;
+    out.tone_map = instance.tone_map;
+    out.local_pos = in.uv * instance.size;
+    out.checker = instance.checker;
+    out.adjust = instance.adjust;
+    out.channel = instance.channel;
 
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
-    let tex_color = textureSample(texture, texture_sampler, in.uv);
-    
+    var tex_color = textureSample(texture, texture_sampler, in.uv);
+
+    let exposed = max(tex_color.rgb * in.exposure, vec3<f32>(0.));
+
+    switch in.tone_map {
+        case 1u: {
+            tex_color = vec4<f32>(reinhard(exposed), tex_color.a);
+        }
+        case 2u: {
+            tex_color = vec4<f32>(aces(exposed), tex_color.a);
+        }
+        default: {}
+    }
+
+    tex_color = adjust_color(tex_color, in.adjust, in.channel);
+
+    // Composite over the checkerboard so the image ends up opaque, other than when fading
+    if in.checker > 0. {
+        let backdrop = checkerboard(in.local_pos, in.checker);
+        tex_color = vec4<f32>(mix(backdrop, tex_color.rgb, tex_color.a), 1.);
+    }
+
     return tex_color * in.color;
 }
 
 //====================================================================
-
@@ -0,0 +1,252 @@
+//====================================================================
+
+use cabat::{
+    common::WindowSize,
+    runner::tools::MouseInput,
+    shipyard_tools::{prelude::*, UniqueTools},
+};
+use shipyard::{AllStoragesView, EntitiesViewMut, EntityId, Get, Unique, ViewMut};
+
+use crate::{
+    images::Pos,
+    keybinds::{Action, ActionInput},
+    layout::{LayoutManager, ScrollController},
+    renderer::{
+        camera::{MainCamera, UiCamera},
+        rect_pipeline::RectShape,
+    },
+    tools::aabb_point,
+};
+
+//====================================================================
+
+pub(crate) struct ScrollbarPlugin;
+
+impl Plugin for ScrollbarPlugin {
+    fn build(self, workload_builder: &WorkloadBuilder) {
+        workload_builder
+            .add_workload(Stages::Setup, sys_setup_scrollbar)
+            .add_workload(Stages::Update, sys_drag_scrollbar)
+            .add_workload_post(Stages::Update, sys_update_scrollbar);
+    }
+}
+
+//====================================================================
+
+#[derive(Unique)]
+pub struct Scrollbar {
+    width: f32,
+    margin: f32,
+    min_thumb_height: f32,
+
+    track_color: [f32; 4],
+    thumb_color: [f32; 4],
+    thumb_drag_color: [f32; 4],
+
+    // Distance from the thumb's center to where it was grabbed
+    drag: Option<f32>,
+
+    track_id: EntityId,
+    thumb_id: EntityId,
+}
+
+impl Scrollbar {
+    #[inline]
+    fn visible(&self, layout: &LayoutManager) -> bool {
+        !layout.fullscreen() && layout.image_count() > 0
+    }
+
+    /// Center and size of the track in screen space, along the right edge of the grid
+    fn track(&self, layout: &LayoutManager, window_size: &WindowSize) -> (glam::Vec2, glam::Vec2) {
+        let x = -window_size.width_f32() / 2. + layout.width() - self.margin - self.width / 2.;
+
+        (
+            glam::vec2(x, 0.),
+            glam::vec2(self.width, window_size.height_f32() - self.margin * 2.),
+        )
+    }
+
+    /// Height of the thumb and how far along the track it is, from 0 (top) to 1 (bottom)
+    fn thumb(&self, layout: &LayoutManager, track_height: f32, camera_y: f32) -> (f32, f32) {
+        let (min_y, max_y) = layout.scroll_bounds();
+        let range = (max_y - min_y).max(1.);
+
+        let height = (track_height * track_height / (range + track_height))
+            .max(self.min_thumb_height)
+            .min(track_height);
+        let progress = ((max_y - camera_y) / range).clamp(0., 1.);
+
+        (height, progress)
+    }
+
+    /// Whether the given screen space point is over the scrollbar
+    pub fn contains(
+        &self,
+        layout: &LayoutManager,
+        window_size: &WindowSize,
+        point: glam::Vec2,
+    ) -> bool {
+        if !self.visible(layout) {
+            return false;
+        }
+
+        let (track_pos, track_size) = self.track(layout, window_size);
+        aabb_point(point, track_pos, track_size + self.margin * 2.)
+    }
+
+    #[inline]
+    pub fn dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+}
+
+//====================================================================
+
+fn sys_setup_scrollbar(
+    all_storages: AllStoragesView,
+    mut entities: EntitiesViewMut,
+
+    mut vm_pos: ViewMut<Pos>,
+    mut vm_rect: ViewMut<RectShape>,
+) {
+    let track_color = [1., 1., 1., 0.08];
+    let thumb_color = [1., 1., 1., 0.35];
+
+    // Drawn in front of the tiles, outlines and rubber band
+    let track_id = entities.add_entity(
+        (&mut vm_pos, &mut vm_rect),
+        (
+            Pos { x: 0., y: 0. },
+            RectShape::outline(0., 0., 0., [0., 0., 0., 0.])
+                .with_color(track_color)
+                .with_depth(0.2),
+        ),
+    );
+
+    let thumb_id = entities.add_entity(
+        (&mut vm_pos, &mut vm_rect),
+        (
+            Pos { x: 0., y: 0. },
+            RectShape::outline(0., 0., 0., [0., 0., 0., 0.])
+                .with_color(thumb_color)
+                .with_depth(0.1),
+        ),
+    );
+
+    all_storages.add_unique(Scrollbar {
+        width: 10.,
+        margin: 4.,
+        min_thumb_height: 30.,
+
+        track_color,
+        thumb_color,
+        thumb_drag_color: [1., 1., 1., 0.6],
+
+        drag: None,
+
+        track_id,
+        thumb_id,
+    });
+}
+
+fn sys_drag_scrollbar(
+    window_size: Res<WindowSize>,
+    layout: Res<LayoutManager>,
+    camera: Res<MainCamera>,
+    ui_camera: Res<UiCamera>,
+    mut scroll: ResMut<ScrollController>,
+    mut scrollbar: ResMut<Scrollbar>,
+
+    actions: ActionInput,
+    mouse: Res<MouseInput>,
+) {
+    if !scrollbar.visible(&layout) {
+        scrollbar.drag = None;
+        return;
+    }
+
+    let mouse_pos = ui_camera.raw.screen_to_camera(mouse.screen_pos());
+
+    let (track_pos, track_size) = scrollbar.track(&layout, &window_size);
+    let (thumb_height, progress) = scrollbar.thumb(&layout, track_size.y, camera.raw.translation.y);
+
+    let track_top = track_pos.y + track_size.y / 2.;
+    let travel = (track_size.y - thumb_height).max(1.);
+    let thumb_y = track_top - thumb_height / 2. - progress * travel;
+
+    let (min_y, max_y) = layout.scroll_bounds();
+
+    // Camera position that puts the thumb's center at the given height
+    let camera_y = |center: f32| {
+        let progress = ((track_top - thumb_height / 2. - center) / travel).clamp(0., 1.);
+        max_y - progress * (max_y - min_y)
+    };
+
+    if actions.just_pressed(Action::Select) && scrollbar.contains(&layout, &window_size, mouse_pos)
+    {
+        let over_thumb = (mouse_pos.y - thumb_y).abs() <= thumb_height / 2.;
+
+        match over_thumb {
+            true => scrollbar.drag = Some(mouse_pos.y - thumb_y),
+            // Clicking the track jumps there, then keeps dragging from the thumb's center
+            false => {
+                scroll.jump_to(camera_y(mouse_pos.y));
+                scrollbar.drag = Some(0.);
+            }
+        }
+
+        return;
+    }
+
+    let grab = match scrollbar.drag {
+        Some(grab) => grab,
+        None => return,
+    };
+
+    if !actions.pressed(Action::Select) {
+        scrollbar.drag = None;
+        return;
+    }
+
+    scroll.jump_to(camera_y(mouse_pos.y - grab));
+}
+
+fn sys_update_scrollbar(
+    window_size: Res<WindowSize>,
+    layout: Res<LayoutManager>,
+    camera: Res<MainCamera>,
+    scrollbar: Res<Scrollbar>,
+
+    mut vm_pos: ViewMut<Pos>,
+    mut vm_rect: ViewMut<RectShape>,
+) {
+    let (track_pos, track_size) = scrollbar.track(&layout, &window_size);
+    let (thumb_height, progress) = scrollbar.thumb(&layout, track_size.y, camera.raw.translation.y);
+
+    let track_top = track_pos.y + track_size.y / 2.;
+    let thumb_y = track_top - thumb_height / 2. - progress * (track_size.y - thumb_height);
+
+    let visible = scrollbar.visible(&layout) as u8 as f32;
+
+    // Rects are drawn in grid space so follow the camera
+    let offset = glam::vec2(camera.raw.translation.x, camera.raw.translation.y);
+
+    let (mut pos, mut rect) = (&mut vm_pos, &mut vm_rect).get(scrollbar.track_id).unwrap();
+    pos.x = track_pos.x + offset.x;
+    pos.y = track_pos.y + offset.y;
+    rect.width = track_size.x * visible;
+    rect.height = track_size.y * visible;
+    rect.color = scrollbar.track_color;
+
+    let (mut pos, mut rect) = (&mut vm_pos, &mut vm_rect).get(scrollbar.thumb_id).unwrap();
+    pos.x = track_pos.x + offset.x;
+    pos.y = thumb_y + offset.y;
+    rect.width = track_size.x * visible;
+    rect.height = thumb_height * visible;
+    rect.color = match scrollbar.dragging() {
+        true => scrollbar.thumb_drag_color,
+        false => scrollbar.thumb_color,
+    };
+}
+
+//====================================================================
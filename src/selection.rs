@@ -0,0 +1,318 @@
+//====================================================================
+
+use cabat::{
+    common::WindowSize,
+    renderer::text::{Text2dBuffer, Text2dBufferDescriptor, TextFontSystem},
+    runner::tools::MouseInput,
+    shipyard_tools::{prelude::*, UniqueTools},
+};
+use shipyard::{
+    AllStoragesView, EntitiesView, EntitiesViewMut, EntityId, Get, IntoIter, IntoWithId, Remove,
+    Unique, View, ViewMut,
+};
+
+use crate::{
+    images::{ImageFocused, ImageHovered, ImageIndex, ImageSelected, ImageVisible, Pos},
+    keybinds::{Action, ActionInput},
+    layout::{set_focused, LayoutManager, SelectedEvent},
+    renderer::{
+        camera::{MainCamera, UiCamera},
+        rect_pipeline::RectShape,
+    },
+    scrollbar::Scrollbar,
+    tools::{aabb, aabb_point},
+};
+
+//====================================================================
+
+pub(crate) struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(self, workload_builder: &WorkloadBuilder) {
+        workload_builder
+            .add_workload(Stages::Setup, sys_setup_selection)
+            .add_workload(Stages::Update, sys_select_images)
+            .add_workload_post(Stages::Update, sys_update_selection_status);
+    }
+}
+
+//====================================================================
+
+#[derive(Unique)]
+pub struct Selection {
+    // Index of the last clicked image, used as the start of shift-click ranges
+    anchor: Option<u32>,
+    band: Option<SelectionBand>,
+
+    band_id: EntityId,
+    status_id: EntityId,
+    // Last text shaped into the status line
+    status_text: String,
+}
+
+struct SelectionBand {
+    start: glam::Vec2,
+    // Images that stay selected regardless of what the band covers
+    base: Vec<EntityId>,
+}
+
+//====================================================================
+
+fn sys_setup_selection(
+    all_storages: AllStoragesView,
+    mut entities: EntitiesViewMut,
+
+    mut font_system: ResMut<TextFontSystem>,
+    mut vm_text_buffer: ViewMut<Text2dBuffer>,
+    mut vm_pos: ViewMut<Pos>,
+    mut vm_rect: ViewMut<RectShape>,
+) {
+    let band_id = entities.add_entity(
+        (&mut vm_pos, &mut vm_rect),
+        (
+            Pos { x: 0., y: 0. },
+            RectShape::outline(0., 0., 1.5, [0.3, 0.5, 1., 0.9])
+                .with_color([0.3, 0.5, 1., 0.15])
+                .with_depth(0.5),
+        ),
+    );
+
+    let status_id = entities.add_entity(
+        &mut vm_text_buffer,
+        Text2dBuffer::new(
+            font_system.inner_mut(),
+            &Text2dBufferDescriptor {
+                ..Default::default()
+            },
+        ),
+    );
+
+    all_storages.add_unique(Selection {
+        anchor: None,
+        band: None,
+
+        band_id,
+        status_id,
+        status_text: String::new(),
+    });
+}
+
+fn sys_select_images(
+    mut events: ResMut<EventHandler>,
+    actions: ActionInput,
+    mouse: Res<MouseInput>,
+
+    window_size: Res<WindowSize>,
+    layout: Res<LayoutManager>,
+    main_camera: Res<MainCamera>,
+    ui_camera: Res<UiCamera>,
+    scrollbar: Res<Scrollbar>,
+    mut selection: ResMut<Selection>,
+
+    entities: EntitiesView,
+    v_hovered: View<ImageHovered>,
+    v_index: View<ImageIndex>,
+    v_visible: View<ImageVisible>,
+    mut vm_pos: ViewMut<Pos>,
+    mut vm_rect: ViewMut<RectShape>,
+    mut vm_selected: ViewMut<ImageSelected>,
+    mut vm_focused: ViewMut<ImageFocused>,
+) {
+    let ctrl = actions.pressed(Action::ToggleSelectModifier);
+    let shift = actions.pressed(Action::RangeSelectModifier);
+
+    // Closing the viewer leaves the selection alone. Deselecting closes the viewer first.
+    let close = actions.just_pressed(Action::CloseViewer);
+
+    if close || actions.just_pressed(Action::Deselect) {
+        if layout.selected() || close {
+            events.add_event(SelectedEvent {
+                selected: None,
+                fade: None,
+            });
+        } else {
+            vm_selected.clear();
+            selection.anchor = None;
+        }
+
+        cancel_band(&mut selection, &mut vm_rect);
+        return;
+    }
+
+    if layout.fullscreen() {
+        cancel_band(&mut selection, &mut vm_rect);
+        return;
+    }
+
+    let mouse_pos = main_camera.raw.screen_to_camera(mouse.screen_pos());
+
+    if actions.just_pressed(Action::Select) {
+        let ui_mouse_pos = ui_camera.raw.screen_to_camera(mouse.screen_pos());
+
+        let (pane_pos, pane_size) = layout.viewer_pane(&window_size);
+        let over_viewer = layout.selected() && aabb_point(ui_mouse_pos, pane_pos, pane_size);
+
+        // Clicking a group header collapses it instead
+        if over_viewer
+            || scrollbar.contains(&layout, &window_size, ui_mouse_pos)
+            || layout.header_at(&window_size, mouse_pos).is_some()
+        {
+            return;
+        }
+
+        let hovered = (&v_hovered, &v_index)
+            .iter()
+            .with_id()
+            .next()
+            .map(|(id, (_, index))| (id, index.index));
+
+        match (hovered, selection.anchor) {
+            // Select everything between the anchor and the clicked image
+            (Some((id, index)), Some(anchor)) if shift => {
+                if !ctrl {
+                    vm_selected.clear();
+                }
+
+                let range = anchor.min(index)..=anchor.max(index);
+                v_index
+                    .iter()
+                    .with_id()
+                    .filter(|(_, image_index)| range.contains(&image_index.index))
+                    .for_each(|(id, _)| {
+                        entities.add_component(id, &mut vm_selected, ImageSelected)
+                    });
+
+                set_focused(&entities, &mut vm_focused, id);
+            }
+
+            // Toggle the clicked image without touching the rest of the selection
+            (Some((id, index)), _) if ctrl || shift => {
+                match vm_selected.contains(id) {
+                    true => {
+                        vm_selected.remove(id);
+                    }
+                    false => entities.add_component(id, &mut vm_selected, ImageSelected),
+                }
+
+                selection.anchor = Some(index);
+                set_focused(&entities, &mut vm_focused, id);
+            }
+
+            (Some((id, index)), _) => {
+                log::debug!("New image selected with id '{:?}'", id);
+
+                set_focused(&entities, &mut vm_focused, id);
+
+                vm_selected.clear();
+                entities.add_component(id, &mut vm_selected, ImageSelected);
+                selection.anchor = Some(index);
+
+                events.add_event(SelectedEvent {
+                    selected: Some(id),
+                    fade: None,
+                });
+            }
+
+            // Clicked on empty space - start a rubber band
+            (None, _) => {
+                let base = match ctrl || shift {
+                    true => vm_selected.iter().with_id().map(|(id, _)| id).collect(),
+                    false => {
+                        vm_selected.clear();
+                        Vec::new()
+                    }
+                };
+
+                selection.band = Some(SelectionBand {
+                    start: mouse_pos,
+                    base,
+                });
+            }
+        }
+    }
+
+    if selection.band.is_none() {
+        return;
+    }
+
+    if !actions.pressed(Action::Select) {
+        cancel_band(&mut selection, &mut vm_rect);
+        return;
+    }
+
+    let band = selection.band.as_ref().unwrap();
+
+    let band_pos = (band.start + mouse_pos) / 2.;
+    let band_size = (mouse_pos - band.start).abs();
+
+    vm_selected.clear();
+    band.base
+        .iter()
+        .for_each(|id| entities.add_component(*id, &mut vm_selected, ImageSelected));
+
+    let tile_size = layout.tile_size();
+    (&vm_pos, &v_index, &v_visible)
+        .iter()
+        .with_id()
+        .filter(|(_, (pos, _, _))| aabb(band_pos, band_size, glam::vec2(pos.x, pos.y), tile_size))
+        .for_each(|(id, _)| entities.add_component(id, &mut vm_selected, ImageSelected));
+
+    let band_id = selection.band_id;
+
+    let mut pos = (&mut vm_pos).get(band_id).unwrap();
+    pos.x = band_pos.x;
+    pos.y = band_pos.y;
+
+    let mut rect = (&mut vm_rect).get(band_id).unwrap();
+    rect.width = band_size.x;
+    rect.height = band_size.y;
+}
+
+fn cancel_band(selection: &mut Selection, vm_rect: &mut ViewMut<RectShape>) {
+    if selection.band.take().is_none() {
+        return;
+    }
+
+    let mut rect = (&mut *vm_rect).get(selection.band_id).unwrap();
+    rect.width = 0.;
+    rect.height = 0.;
+}
+
+//====================================================================
+
+fn sys_update_selection_status(
+    window_size: Res<WindowSize>,
+    layout: Res<LayoutManager>,
+    mut selection: ResMut<Selection>,
+
+    mut font_system: ResMut<TextFontSystem>,
+    mut vm_text_buffer: ViewMut<Text2dBuffer>,
+
+    v_selected: View<ImageSelected>,
+    v_index: View<ImageIndex>,
+) {
+    let count = (&v_selected, &v_index).iter().count();
+
+    let text = match count == 0 || layout.fullscreen() {
+        true => String::new(),
+        false => format!("{} selected", count),
+    };
+
+    let mut buffer = (&mut vm_text_buffer).get(selection.status_id).unwrap();
+
+    // Bottom left corner of the window in screen space
+    buffer.pos.0 = 12.;
+    buffer.pos.1 = window_size.height_f32() - 32.;
+
+    buffer.bounds.top = 0;
+    buffer.bounds.bottom = window_size.height() as i32;
+    buffer.bounds.left = 0;
+    buffer.bounds.right = window_size.width() as i32;
+
+    if selection.status_text != text {
+        buffer.set_text(font_system.inner_mut(), &text);
+        selection.status_text = text;
+    }
+}
+
+//====================================================================
@@ -0,0 +1,212 @@
+//====================================================================
+
+use cabat::{
+    common::WindowSize,
+    renderer::text::{Text2dBuffer, Text2dBufferDescriptor, TextFontSystem},
+};
+use shipyard::{EntitiesViewMut, EntityId, Get, ViewMut};
+
+use crate::{
+    images::Pos,
+    keybinds::{Action, ActionInput},
+    renderer::rect_pipeline::{RectShape, UiRect},
+    tools::aabb_point,
+};
+
+//====================================================================
+
+/// Center and size of a slider's track in screen space
+pub type Track = (glam::Vec2, glam::Vec2);
+
+/// Horizontal slider drawn as a track, a handle and a label. Its owner decides
+/// where the track goes and what the handle's progress along it means.
+pub struct Slider {
+    dragging: bool,
+
+    bar_height: f32,
+    handle_width: f32,
+    margin: f32,
+
+    track_color: [f32; 4],
+    handle_color: [f32; 4],
+    handle_drag_color: [f32; 4],
+
+    track_id: EntityId,
+    handle_id: EntityId,
+    label_id: EntityId,
+    // Last text shaped into the label
+    label_text: String,
+}
+
+impl Slider {
+    pub fn new(
+        entities: &mut EntitiesViewMut,
+        font_system: &mut TextFontSystem,
+
+        vm_pos: &mut ViewMut<Pos>,
+        vm_rect: &mut ViewMut<RectShape>,
+        vm_ui: &mut ViewMut<UiRect>,
+        vm_text: &mut ViewMut<Text2dBuffer>,
+    ) -> Self {
+        let track_color = [1., 1., 1., 0.15];
+        let handle_color = [1., 1., 1., 0.7];
+
+        let track_id = entities.add_entity(
+            (&mut *vm_pos, &mut *vm_rect, &mut *vm_ui),
+            (
+                Pos::default(),
+                RectShape::outline(0., 0., 0., [0., 0., 0., 0.])
+                    .with_color(track_color)
+                    .with_depth(0.2),
+                UiRect,
+            ),
+        );
+
+        let handle_id = entities.add_entity(
+            (&mut *vm_pos, &mut *vm_rect, &mut *vm_ui),
+            (
+                Pos::default(),
+                RectShape::outline(0., 0., 0., [0., 0., 0., 0.])
+                    .with_color(handle_color)
+                    .with_depth(0.1),
+                UiRect,
+            ),
+        );
+
+        let label_id = entities.add_entity(
+            &mut *vm_text,
+            Text2dBuffer::new(font_system.inner_mut(), &Text2dBufferDescriptor::default()),
+        );
+
+        Self {
+            dragging: false,
+
+            bar_height: 6.,
+            handle_width: 10.,
+            margin: 24.,
+
+            track_color,
+            handle_color,
+            handle_drag_color: [1., 1., 1., 1.],
+
+            track_id,
+            handle_id,
+            label_id,
+            label_text: String::new(),
+        }
+    }
+
+    #[inline]
+    pub fn dragging(&self) -> bool {
+        self.dragging
+    }
+
+    #[inline]
+    pub fn stop_dragging(&mut self) {
+        self.dragging = false;
+    }
+
+    #[inline]
+    pub fn bar_height(&self) -> f32 {
+        self.bar_height
+    }
+
+    #[inline]
+    pub fn margin(&self) -> f32 {
+        self.margin
+    }
+
+    /// Whether the given screen space point is over the track
+    pub fn contains(&self, (track_pos, track_size): Track, point: glam::Vec2) -> bool {
+        // Generous hit area since the bar itself is thin
+        aabb_point(point, track_pos, track_size + glam::vec2(0., self.margin))
+    }
+
+    /// Start dragging when pressed over the track and stop once released. Returns
+    /// how far along the track the mouse is while dragging, from 0 to 1.
+    pub fn drag(
+        &mut self,
+        track: Track,
+        actions: &ActionInput,
+        mouse_pos: glam::Vec2,
+    ) -> Option<f32> {
+        if actions.just_pressed(Action::Select) && self.contains(track, mouse_pos) {
+            self.dragging = true;
+        }
+
+        if !self.dragging {
+            return None;
+        }
+
+        if !actions.pressed(Action::Select) {
+            self.dragging = false;
+            return None;
+        }
+
+        let (track_pos, track_size) = track;
+        Some(
+            ((mouse_pos.x - (track_pos.x - track_size.x / 2.)) / track_size.x.max(1.))
+                .clamp(0., 1.),
+        )
+    }
+
+    /// Place the track and handle, hidden sliders are shrunk to nothing
+    pub fn update_bar(
+        &self,
+        (track_pos, track_size): Track,
+        progress: f32,
+        visible: bool,
+
+        vm_pos: &mut ViewMut<Pos>,
+        vm_rect: &mut ViewMut<RectShape>,
+    ) {
+        let visible = visible as u8 as f32;
+
+        let (mut pos, mut rect) = (&mut *vm_pos, &mut *vm_rect).get(self.track_id).unwrap();
+        pos.x = track_pos.x;
+        pos.y = track_pos.y;
+        rect.width = track_size.x * visible;
+        rect.height = track_size.y * visible;
+        rect.color = self.track_color;
+
+        let (mut pos, mut rect) = (&mut *vm_pos, &mut *vm_rect).get(self.handle_id).unwrap();
+        pos.x = track_pos.x - track_size.x / 2. + progress * track_size.x;
+        pos.y = track_pos.y;
+        rect.width = self.handle_width * visible;
+        rect.height = track_size.y * 3. * visible;
+        rect.color = match self.dragging {
+            true => self.handle_drag_color,
+            false => self.handle_color,
+        };
+    }
+
+    /// Place the label in line with the start of the track, at the given screen
+    /// space height. The text is only reshaped when it changes.
+    pub fn update_label(
+        &mut self,
+        (track_pos, track_size): Track,
+        label_y: f32,
+        text: String,
+
+        window_size: &WindowSize,
+        font_system: &mut TextFontSystem,
+        vm_text: &mut ViewMut<Text2dBuffer>,
+    ) {
+        let mut label = (&mut *vm_text).get(self.label_id).unwrap();
+
+        label.pos.0 = window_size.width_f32() / 2. + track_pos.x - track_size.x / 2.;
+        label.pos.1 = label_y;
+
+        label.bounds.top = 0;
+        label.bounds.bottom = window_size.height() as i32;
+        label.bounds.left = 0;
+        label.bounds.right = window_size.width() as i32;
+
+        if self.label_text != text {
+            label.set_text(font_system.inner_mut(), &text);
+            self.label_text = text;
+        }
+    }
+}
+
+//====================================================================
@@ -0,0 +1,356 @@
+//====================================================================
+
+use std::time::Duration;
+
+use cabat::{
+    runner::tools::Time,
+    shipyard_tools::{prelude::*, UniqueTools},
+};
+use shipyard::{
+    AllStoragesView, EntitiesView, EntityId, IntoIter, IntoWithId, IntoWorkload, Remove, Unique,
+    View, ViewMut,
+};
+
+use crate::{
+    images::{Color, ImageDirty, ImageFade, ImageFocused, ImageIndex, ImageSelected, ToRemove},
+    keybinds::{Action, ActionInput},
+    layout::{set_focused, LayoutManager, SelectedEvent},
+};
+
+//====================================================================
+
+pub(crate) struct SlideshowPlugin;
+
+impl Plugin for SlideshowPlugin {
+    fn build(self, workload_builder: &WorkloadBuilder) {
+        workload_builder
+            .add_workload(Stages::Setup, sys_setup_slideshow)
+            .add_workload(
+                Stages::Update,
+                (sys_control_slideshow, sys_tick_slideshow).into_sequential_workload(),
+            )
+            .add_workload_post(Stages::Update, sys_tick_fades);
+    }
+}
+
+//====================================================================
+
+#[derive(Unique)]
+pub struct Slideshow {
+    active: bool,
+    paused: bool,
+    timer: Duration,
+
+    interval: Duration,
+    min_interval: Duration,
+    interval_step: Duration,
+
+    shuffle: bool,
+    looping: bool,
+    crossfade: Option<Duration>,
+
+    // Image indexes in the order they're shown
+    order: Vec<u32>,
+    position: usize,
+    rng: u64,
+}
+
+impl Default for Slideshow {
+    fn default() -> Self {
+        // Seed doesn't need to be good, just different between runs
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or(0);
+
+        Self {
+            active: false,
+            paused: false,
+            timer: Duration::ZERO,
+
+            interval: Duration::from_secs(4),
+            min_interval: Duration::from_millis(500),
+            interval_step: Duration::from_millis(500),
+
+            shuffle: false,
+            looping: true,
+            crossfade: Some(Duration::from_millis(400)),
+
+            order: Vec::new(),
+            position: 0,
+            rng: seed | 1,
+        }
+    }
+}
+
+impl Slideshow {
+    fn rebuild_order(&mut self, image_count: u32, current: u32) {
+        self.order = (0..image_count).collect();
+
+        if self.shuffle {
+            // Fisher-Yates with a xorshift generator
+            for i in (1..self.order.len()).rev() {
+                self.rng ^= self.rng << 13;
+                self.rng ^= self.rng >> 7;
+                self.rng ^= self.rng << 17;
+
+                let j = (self.rng % (i as u64 + 1)) as usize;
+                self.order.swap(i, j);
+            }
+        }
+
+        self.position = self
+            .order
+            .iter()
+            .position(|index| *index == current)
+            .unwrap_or(0);
+    }
+
+    #[inline]
+    pub fn active(&self) -> bool {
+        self.active
+    }
+
+    fn stop(&mut self) {
+        log::info!("Slideshow stopped");
+        self.active = false;
+        self.paused = false;
+    }
+}
+
+//====================================================================
+
+fn sys_setup_slideshow(all_storages: AllStoragesView) {
+    all_storages.add_unique(Slideshow::default());
+}
+
+fn sys_control_slideshow(
+    mut events: ResMut<EventHandler>,
+    actions: ActionInput,
+    layout: Res<LayoutManager>,
+    mut slideshow: ResMut<Slideshow>,
+
+    entities: EntitiesView,
+    v_index: View<ImageIndex>,
+    mut vm_selected: ViewMut<ImageSelected>,
+    mut vm_focused: ViewMut<ImageFocused>,
+) {
+    // Stop if the selected view was closed from elsewhere
+    if slideshow.active && !layout.selected() {
+        slideshow.stop();
+    }
+
+    if actions.just_pressed(Action::SlideshowShuffle) {
+        slideshow.shuffle = !slideshow.shuffle;
+        slideshow.order.clear();
+        log::info!("Slideshow shuffle: {}", slideshow.shuffle);
+    }
+
+    if actions.just_pressed(Action::SlideshowLoop) {
+        slideshow.looping = !slideshow.looping;
+        log::info!("Slideshow loop: {}", slideshow.looping);
+    }
+
+    if actions.just_pressed(Action::SlideshowCrossfade) {
+        slideshow.crossfade = match slideshow.crossfade {
+            Some(_) => None,
+            None => Some(Duration::from_millis(400)),
+        };
+        log::info!("Slideshow crossfade: {:?}", slideshow.crossfade);
+    }
+
+    if actions.just_pressed(Action::SlideshowFaster) {
+        slideshow.interval = slideshow
+            .interval
+            .saturating_sub(slideshow.interval_step)
+            .max(slideshow.min_interval);
+        log::info!("Slideshow interval: {:?}", slideshow.interval);
+    }
+
+    if actions.just_pressed(Action::SlideshowSlower) {
+        let step = slideshow.interval_step;
+        slideshow.interval += step;
+        log::info!("Slideshow interval: {:?}", slideshow.interval);
+    }
+
+    if slideshow.active && actions.just_pressed(Action::SlideshowPause) {
+        slideshow.paused = !slideshow.paused;
+        log::info!("Slideshow paused: {}", slideshow.paused);
+    }
+
+    if !actions.just_pressed(Action::SlideshowToggle) {
+        return;
+    }
+
+    if slideshow.active {
+        slideshow.stop();
+        return;
+    }
+
+    if layout.image_count() == 0 {
+        return;
+    }
+
+    // Start from the selected image, then the focused one, then the beginning
+    let current = (&v_index, &vm_selected)
+        .iter()
+        .map(|(index, _)| index.index)
+        .next()
+        .or_else(|| {
+            (&v_index, &vm_focused)
+                .iter()
+                .map(|(index, _)| index.index)
+                .next()
+        })
+        .unwrap_or(0);
+
+    log::info!("Slideshow started");
+
+    slideshow.active = true;
+    slideshow.paused = false;
+    slideshow.timer = Duration::ZERO;
+    slideshow.rebuild_order(layout.image_count(), current);
+
+    if !layout.selected() {
+        if let Some(id) = find_index(&v_index, current) {
+            set_focused(&entities, &mut vm_focused, id);
+
+            vm_selected.clear();
+            entities.add_component(id, &mut vm_selected, ImageSelected);
+
+            events.add_event(SelectedEvent {
+                selected: Some(id),
+                fade: None,
+            });
+        }
+    }
+}
+
+fn sys_tick_slideshow(
+    mut events: ResMut<EventHandler>,
+    time: Res<Time>,
+    layout: Res<LayoutManager>,
+    mut slideshow: ResMut<Slideshow>,
+
+    entities: EntitiesView,
+    v_index: View<ImageIndex>,
+    mut vm_selected: ViewMut<ImageSelected>,
+    mut vm_focused: ViewMut<ImageFocused>,
+) {
+    if !slideshow.active || slideshow.paused {
+        return;
+    }
+
+    slideshow.timer += *time.delta();
+
+    if slideshow.timer < slideshow.interval {
+        return;
+    }
+
+    let interval = slideshow.interval;
+    slideshow.timer -= interval;
+
+    // The shown image is always focused, even when more images are selected
+    let current = (&v_index, &vm_focused)
+        .iter()
+        .map(|(index, _)| index.index)
+        .next()
+        .unwrap_or(0);
+
+    // Pick up images that finished loading since the slideshow started
+    if slideshow.order.len() != layout.image_count() as usize {
+        slideshow.rebuild_order(layout.image_count(), current);
+    }
+
+    // Carry on from wherever the user navigated to in the meantime
+    if let Some(position) = slideshow.order.iter().position(|index| *index == current) {
+        slideshow.position = position;
+    }
+
+    slideshow.position += 1;
+
+    if slideshow.position >= slideshow.order.len() {
+        if !slideshow.looping {
+            slideshow.stop();
+            return;
+        }
+
+        match slideshow.shuffle {
+            true => {
+                slideshow.rebuild_order(layout.image_count(), current);
+                slideshow.position = 0;
+            }
+            false => slideshow.position = 0,
+        }
+    }
+
+    let id = match find_index(&v_index, slideshow.order[slideshow.position]) {
+        Some(id) => id,
+        None => return,
+    };
+
+    set_focused(&entities, &mut vm_focused, id);
+
+    vm_selected.clear();
+    entities.add_component(id, &mut vm_selected, ImageSelected);
+
+    events.add_event(SelectedEvent {
+        selected: Some(id),
+        fade: slideshow.crossfade,
+    });
+}
+
+fn find_index(v_index: &View<ImageIndex>, index: u32) -> Option<EntityId> {
+    v_index
+        .iter()
+        .with_id()
+        .find(|(_, image_index)| image_index.index == index)
+        .map(|(id, _)| id)
+}
+
+//====================================================================
+
+fn sys_tick_fades(
+    time: Res<Time>,
+
+    entities: EntitiesView,
+    mut vm_fade: ViewMut<ImageFade>,
+    mut vm_color: ViewMut<Color>,
+    mut vm_dirty: ViewMut<ImageDirty>,
+    mut vm_remove: ViewMut<ToRemove>,
+) {
+    let mut finished = Vec::new();
+
+    (&mut vm_fade, &mut vm_color)
+        .iter()
+        .with_id()
+        .for_each(|(id, (mut fade, mut color))| {
+            fade.elapsed += *time.delta();
+
+            let progress = match fade.duration.is_zero() {
+                true => 1.,
+                false => (fade.elapsed.as_secs_f32() / fade.duration.as_secs_f32()).min(1.),
+            };
+
+            color.a = match fade.out {
+                true => 1. - progress,
+                false => progress,
+            };
+
+            entities.add_component(id, &mut vm_dirty, ImageDirty);
+
+            if progress >= 1. {
+                finished.push((id, fade.out));
+            }
+        });
+
+    finished.into_iter().for_each(|(id, out)| {
+        vm_fade.remove(id);
+
+        if out {
+            entities.add_component(id, &mut vm_remove, ToRemove);
+        }
+    });
+}
+
+//====================================================================
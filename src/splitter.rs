@@ -0,0 +1,193 @@
+//====================================================================
+
+use cabat::{
+    common::WindowSize,
+    runner::tools::MouseInput,
+    shipyard_tools::{prelude::*, UniqueTools},
+};
+use shipyard::{AllStoragesView, EntitiesViewMut, EntityId, Get, Unique, ViewMut};
+
+use crate::{
+    config::{Settings, SettingsFile},
+    images::Pos,
+    keybinds::{Action, ActionInput},
+    layout::{LayoutManager, RelayoutEvent},
+    renderer::{
+        camera::UiCamera,
+        rect_pipeline::{RectShape, UiRect},
+    },
+    tools::aabb_point,
+};
+
+//====================================================================
+
+pub(crate) struct SplitterPlugin;
+
+impl Plugin for SplitterPlugin {
+    fn build(self, workload_builder: &WorkloadBuilder) {
+        workload_builder
+            .add_workload(Stages::Setup, sys_setup_splitter)
+            .add_workload(Stages::Update, sys_drag_splitter)
+            .add_workload_post(Stages::Update, sys_update_splitter);
+    }
+}
+
+//====================================================================
+
+#[derive(Unique)]
+pub struct Splitter {
+    width: f32,
+    dragging: bool,
+
+    color: [f32; 4],
+    hover_color: [f32; 4],
+
+    id: EntityId,
+}
+
+impl Splitter {
+    #[inline]
+    fn visible(&self, layout: &LayoutManager) -> bool {
+        layout.selected() && !layout.fullscreen()
+    }
+
+    /// Center and size of the divider in screen space. It sits on the pane
+    /// side of the split so it doesn't overlap the grid's scrollbar.
+    fn rect(&self, layout: &LayoutManager, window_size: &WindowSize) -> (glam::Vec2, glam::Vec2) {
+        let x = -window_size.width_f32() / 2. + layout.width() + self.width / 2.;
+
+        (
+            glam::vec2(x, 0.),
+            glam::vec2(self.width, window_size.height_f32()),
+        )
+    }
+
+    /// Whether the given screen space point is over the divider
+    pub fn contains(
+        &self,
+        layout: &LayoutManager,
+        window_size: &WindowSize,
+        point: glam::Vec2,
+    ) -> bool {
+        if !self.visible(layout) {
+            return false;
+        }
+
+        let (pos, size) = self.rect(layout, window_size);
+        aabb_point(point, pos, size)
+    }
+
+    #[inline]
+    pub fn dragging(&self) -> bool {
+        self.dragging
+    }
+}
+
+//====================================================================
+
+fn sys_setup_splitter(
+    all_storages: AllStoragesView,
+    mut entities: EntitiesViewMut,
+
+    mut vm_pos: ViewMut<Pos>,
+    mut vm_rect: ViewMut<RectShape>,
+    mut vm_ui: ViewMut<UiRect>,
+) {
+    let color = [0.25, 0.25, 0.25, 1.];
+
+    let id = entities.add_entity(
+        (&mut vm_pos, &mut vm_rect, &mut vm_ui),
+        (
+            Pos { x: 0., y: 0. },
+            RectShape::outline(0., 0., 0., [0., 0., 0., 0.])
+                .with_color(color)
+                .with_depth(0.1),
+            UiRect,
+        ),
+    );
+
+    all_storages.add_unique(Splitter {
+        width: 6.,
+        dragging: false,
+
+        color,
+        hover_color: [0.45, 0.45, 0.45, 1.],
+
+        id,
+    });
+}
+
+fn sys_drag_splitter(
+    mut events: ResMut<EventHandler>,
+    window_size: Res<WindowSize>,
+    mut layout: ResMut<LayoutManager>,
+    ui_camera: Res<UiCamera>,
+    mut splitter: ResMut<Splitter>,
+    mut settings: ResMut<Settings>,
+    mut settings_file: ResMut<SettingsFile>,
+
+    actions: ActionInput,
+    mouse: Res<MouseInput>,
+) {
+    if !splitter.visible(&layout) {
+        splitter.dragging = false;
+        return;
+    }
+
+    let mouse_pos = ui_camera.raw.screen_to_camera(mouse.screen_pos());
+
+    if actions.just_pressed(Action::Select) && splitter.contains(&layout, &window_size, mouse_pos) {
+        splitter.dragging = true;
+    }
+
+    if !splitter.dragging {
+        return;
+    }
+
+    // Remember the split once the divider is let go
+    if !actions.pressed(Action::Select) {
+        splitter.dragging = false;
+
+        settings.layout.split_ratio = layout.split();
+        settings_file.request_save();
+        return;
+    }
+
+    let split = (mouse_pos.x + window_size.width_f32() / 2.) / window_size.width_f32();
+
+    if split != layout.split() {
+        layout.set_split(split);
+        events.add_event(RelayoutEvent);
+    }
+}
+
+fn sys_update_splitter(
+    window_size: Res<WindowSize>,
+    layout: Res<LayoutManager>,
+    ui_camera: Res<UiCamera>,
+    splitter: Res<Splitter>,
+    mouse: Res<MouseInput>,
+
+    mut vm_pos: ViewMut<Pos>,
+    mut vm_rect: ViewMut<RectShape>,
+) {
+    let (pos, size) = splitter.rect(&layout, &window_size);
+    let visible = splitter.visible(&layout) as u8 as f32;
+
+    let mouse_pos = ui_camera.raw.screen_to_camera(mouse.screen_pos());
+    let highlight = splitter.dragging() || splitter.contains(&layout, &window_size, mouse_pos);
+
+    let (mut rect_pos, mut rect) = (&mut vm_pos, &mut vm_rect).get(splitter.id).unwrap();
+
+    rect_pos.x = pos.x;
+    rect_pos.y = pos.y;
+
+    rect.width = size.x * visible;
+    rect.height = size.y * visible;
+    rect.color = match highlight {
+        true => splitter.hover_color,
+        false => splitter.color,
+    };
+}
+
+//====================================================================
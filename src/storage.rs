@@ -4,7 +4,7 @@ use std::{
     env,
     hash::{Hash, Hasher},
     path::{Path, PathBuf},
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 
 use ahash::AHashMap;
@@ -18,21 +18,30 @@ use cabat::{
 };
 use crossbeam_channel::{Receiver, Sender};
 use image::{
-    codecs::gif::GifDecoder, AnimationDecoder, DynamicImage, GenericImage, GenericImageView,
+    codecs::gif::GifDecoder, AnimationDecoder, ColorType, DynamicImage, GenericImage,
+    GenericImageView, ImageDecoder, Rgba32FImage, RgbaImage,
+};
+use shipyard::{
+    AllStoragesView, EntitiesView, IntoIter, IntoWithId, SystemModificator, Unique, View, ViewMut,
+    Workload,
 };
-use shipyard::{AllStoragesView, SystemModificator, Unique, ViewMut, Workload};
 
 use crate::{
-    images::{GifImage, ImageCreator, ImageIndex, ImageMeta, StandardImage},
-    layout::LayoutManager,
+    captions::ImageCaption,
+    config::{Settings, ToneMap},
+    images::{GifImage, ImageCreator, ImageIndex, ImageMeta, ImageSource, StandardImage, ToRemove},
+    layout::{LayoutManager, SelectedEvent},
     renderer::{
+        atlas::THUMBNAIL_SIZE,
         gif::{
-            Gif, MAX_TEXTURE_HEIGHT, MAX_TEXTURE_WIDTH, MAX_USABLE_IMAGE_HEIGHT,
+            Gif, GifFrameDelay, MAX_TEXTURE_HEIGHT, MAX_TEXTURE_WIDTH, MAX_USABLE_IMAGE_HEIGHT,
             MAX_USABLE_IMAGE_WIDTH,
         },
-        gif2d_pipeline::{Gif2dInstance, Gif2dInstanceRaw, Gif2dPipeline},
-        texture2d_pipeline::{Texture2dInstance, Texture2dInstanceRaw, Texture2dPipeline},
+        gif2d_pipeline::Gif2dPipeline,
+        hdr::{self, HdrTexture},
+        texture2d_pipeline::Texture2dPipeline,
     },
+    tools::civil_from_time,
 };
 
 //====================================================================
@@ -87,11 +96,15 @@ pub type TextureID = u64;
 #[derive(Unique)]
 pub struct Storage {
     textures: AHashMap<TextureID, TextureData>,
+    // Folder that was opened, used to name the subfolders images were found in
+    root: PathBuf,
+    // Opened folder with links resolved, so it's the same however it was opened
+    canonical_root: PathBuf,
 
     loading: bool,
     to_spawn: Vec<TextureID>,
 
-    _load_kill_sender: Sender<bool>,
+    load_kill_sender: Sender<bool>,
     load_kill_receiver: Receiver<bool>,
 
     image_sender: Sender<ImageChannel>,
@@ -102,11 +115,26 @@ pub struct TextureData {
     pub texture: TextureType,
     pub path: PathBuf,
     pub resolution: Size<u32>,
+    pub info: FileInfo,
+}
+
+/// Details about an image's file, used for grouping and captions
+#[derive(Clone, Default)]
+pub struct FileInfo {
+    // Size before being shrunk to fit in a texture
+    pub resolution: (u32, u32),
+    pub file_size: u64,
+    pub modified: Option<SystemTime>,
+    // Year and month the image was taken, or last modified
+    pub month: Option<(i32, u32)>,
+    // Converted from an embedded colour profile to sRGB
+    pub color_converted: bool,
 }
 
 pub enum TextureType {
     Texture(texture::RawTexture),
-    Gif { gif: Gif, frames: Vec<Duration> },
+    Hdr(HdrTexture),
+    Gif(Gif),
 }
 
 //====================================================================
@@ -115,10 +143,15 @@ enum ImageChannel {
     Finished,
     Image {
         path: PathBuf,
+        info: FileInfo,
         image: DynamicImage,
+        thumbnail: RgbaImage,
+        // Float images with values past 1 that need tone mapping
+        scene_referred: bool,
     },
     Gif {
         path: PathBuf,
+        info: FileInfo,
         image: DynamicImage,
         total_frames: u32,
         frames_per_row: u32,
@@ -136,19 +169,21 @@ impl Storage {
 
         Self {
             textures: AHashMap::new(),
+            root: PathBuf::new(),
+            canonical_root: PathBuf::new(),
 
             loading: false,
             to_spawn: Vec::new(),
 
-            _load_kill_sender: load_kill_sender,
+            load_kill_sender,
             load_kill_receiver,
             image_sender,
             image_receiver,
         }
     }
 
-    pub fn _stop_loading(&mut self) {
-        self._load_kill_sender.send(true).ok();
+    pub fn stop_loading(&mut self) {
+        self.load_kill_sender.send(true).ok();
         self.loading = false;
     }
 
@@ -156,34 +191,98 @@ impl Storage {
     pub fn get_texture(&self, id: TextureID) -> Option<&TextureData> {
         self.textures.get(&id)
     }
+
+    /// Folder the image was found in, relative to the opened folder
+    pub fn folder<'a>(&self, path: &'a Path) -> &'a Path {
+        let parent = path.parent().unwrap_or(Path::new(""));
+        parent.strip_prefix(&self.root).unwrap_or(parent)
+    }
+
+    #[inline]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    #[inline]
+    pub fn canonical_root(&self) -> &Path {
+        &self.canonical_root
+    }
+
+    #[inline]
+    pub fn loading(&self) -> bool {
+        self.loading
+    }
 }
 
-fn sys_load_path(events: Res<EventHandler>, mut storage: ResMut<Storage>) {
-    let to_load = events.get_event::<LoadFolderEvent>().unwrap();
+fn sys_load_path(
+    mut events: ResMut<EventHandler>,
+    settings: Res<Settings>,
+    mut storage: ResMut<Storage>,
+    mut layout: ResMut<LayoutManager>,
+    mut texture_pipeline: ResMut<Texture2dPipeline>,
+    mut gif_pipeline: ResMut<Gif2dPipeline>,
 
-    log::info!("Loading images from path '{:?}'", to_load.path);
+    entities: EntitiesView,
+    v_image: View<StandardImage>,
+    v_gif: View<GifImage>,
+    mut vm_remove: ViewMut<ToRemove>,
+) {
+    let to_load = events.get_event::<LoadFolderEvent>().unwrap().path.clone();
 
-    let dir = std::fs::read_dir(&to_load.path).unwrap();
-    let entries = dir.into_iter().filter_map(|e| e.ok()).collect::<Vec<_>>();
+    log::info!("Loading images from path '{:?}'", to_load);
 
-    let images_to_load = entries
-        .into_iter()
-        .filter_map(|entry| {
-            let path = entry.path();
+    // Replace the images of any folder that was already open
+    if storage.loading {
+        storage.stop_loading();
 
-            if !path.is_file() {
-                return None;
-            }
+        // Drop anything the previous folder's loader already sent
+        while storage.image_receiver.try_recv().is_ok() {}
+    }
 
-            match path.extension() {
-                None => return None,
-                Some(ext) => match ext.to_str() {
-                    Some("jpg") | Some("png") | Some("gif") => Some(path),
-                    _ => {
-                        log::trace!("Skipping file path '{:?}'", &path);
-                        None
-                    }
-                },
+    if !storage.textures.is_empty() {
+        log::info!("Removing {} images", storage.textures.len());
+
+        storage
+            .textures
+            .drain()
+            .for_each(|(id, data)| match data.texture {
+                TextureType::Texture(_) | TextureType::Hdr(_) => {
+                    texture_pipeline.remove_texture(id)
+                }
+                TextureType::Gif(_) => gif_pipeline.remove_gif(id),
+            });
+        storage.to_spawn.clear();
+
+        v_image
+            .iter()
+            .with_id()
+            .map(|(id, _)| id)
+            .chain(v_gif.iter().with_id().map(|(id, _)| id))
+            .for_each(|id| entities.add_component(id, &mut vm_remove, ToRemove));
+
+        layout.clear_images();
+        events.add_event(SelectedEvent {
+            selected: None,
+            fade: None,
+        });
+    }
+
+    storage.canonical_root = std::fs::canonicalize(&to_load).unwrap_or(to_load.clone());
+    storage.root = to_load.clone();
+
+    let mut entries = Vec::new();
+    collect_files(&to_load, settings.library.recursive, &mut entries);
+
+    let images_to_load = entries
+        .into_iter()
+        .filter_map(|path| {
+            let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+            match ext.as_str() {
+                "jpg" | "jpeg" | "png" | "tif" | "tiff" | "exr" | "hdr" | "gif" => Some(path),
+                _ => {
+                    log::trace!("Skipping file path '{:?}'", &path);
+                    None
+                }
             }
         })
         .collect::<Vec<_>>();
@@ -197,28 +296,77 @@ fn sys_load_path(events: Res<EventHandler>, mut storage: ResMut<Storage>) {
 
     let load_kill_receiver = storage.load_kill_receiver.clone();
     let image_sender = storage.image_sender.clone();
+    let tone_map = settings.appearance.tone_map;
 
     // TODO - Spawn multiple threads
-    std::thread::spawn(move || load_images(images_to_load, load_kill_receiver, image_sender));
+    std::thread::spawn(move || {
+        load_images(images_to_load, tone_map, load_kill_receiver, image_sender)
+    });
+}
+
+fn collect_files(dir: &Path, recursive: bool, files: &mut Vec<PathBuf>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(dir) => dir.into_iter().filter_map(|e| e.ok()).map(|e| e.path()),
+        Err(e) => {
+            log::warn!("Failed to read folder '{:?}': {}", dir, e);
+            return;
+        }
+    };
+
+    let mut entries = entries.collect::<Vec<_>>();
+    entries.sort();
+
+    entries.into_iter().for_each(|path| match path.is_dir() {
+        true if recursive => collect_files(&path, recursive, files),
+        true => {}
+        false => files.push(path),
+    });
 }
 
 fn load_images(
     images: Vec<PathBuf>,
+    tone_map: ToneMap,
     load_kill_receiver: Receiver<bool>,
     image_sender: Sender<ImageChannel>,
 ) {
     let duration = std::time::Instant::now();
 
     for path in images.into_iter() {
-        let data = match path.extension() {
+        let mut info = file_info(&path);
+
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+
+        let data = match extension {
             None => {
                 log::trace!("Skipping file path '{:?}'", &path);
                 continue;
             }
-            Some(ext) => match ext.to_str() {
-                Some("jpg") | Some("png") => {
-                    let image_reader = image::ImageReader::open(&path).unwrap();
-                    let image = image_reader.decode().unwrap();
+            Some(ext) => match ext.as_str() {
+                "jpg" | "jpeg" | "png" | "tif" | "tiff" | "exr" | "hdr" => {
+                    // Truncated or unsupported files are skipped rather than ending the loader
+                    let mut decoder = match image::ImageReader::open(&path)
+                        .map_err(image::ImageError::IoError)
+                        .and_then(|reader| reader.into_decoder())
+                    {
+                        Ok(decoder) => decoder,
+                        Err(e) => {
+                            log::warn!("Failed to read image {:?}: {}", &path, e);
+                            continue;
+                        }
+                    };
+                    let icc_profile = decoder.icc_profile().ok().flatten();
+
+                    let image = match DynamicImage::from_decoder(decoder) {
+                        Ok(image) => image,
+                        Err(e) => {
+                            log::warn!("Failed to decode image {:?}: {}", &path, e);
+                            continue;
+                        }
+                    };
+                    info.resolution = image.dimensions();
 
                     let resize_image = image.width() > MAX_USABLE_IMAGE_WIDTH
                         || image.height() > MAX_USABLE_IMAGE_HEIGHT;
@@ -232,10 +380,49 @@ fn load_images(
                         false => image,
                     };
 
-                    ImageChannel::Image { path, image }
+                    let high_bit_depth =
+                        image.color().bytes_per_pixel() > image.color().channel_count();
+                    // 16 bit integer images are already in the display's range
+                    let scene_referred =
+                        matches!(image.color(), ColorType::Rgb32F | ColorType::Rgba32F);
+
+                    // Converted before being made linear, 16 bit images keep their precision
+                    let image = match icc_profile.and_then(|icc| convert_to_srgb(&icc, &image)) {
+                        Some(converted) => {
+                            info.color_converted = true;
+                            converted
+                        }
+                        None => image,
+                    };
+
+                    // Shrunk off the main thread, packed into the grid's atlas
+                    let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+                    let thumbnail = match scene_referred {
+                        true => hdr::tone_map_image(&thumbnail.into_rgba32f(), tone_map),
+                        false => thumbnail.to_rgba8(),
+                    };
+
+                    let image = match high_bit_depth {
+                        true => DynamicImage::ImageRgba32F(to_linear(image)),
+                        false => image,
+                    };
+
+                    ImageChannel::Image {
+                        path,
+                        info,
+                        image,
+                        thumbnail,
+                        scene_referred,
+                    }
                 }
 
-                Some("gif") => load_gif(path).unwrap(),
+                "gif" => match load_gif(path.clone(), info) {
+                    Some(data) => data,
+                    None => {
+                        log::warn!("Failed to load gif {:?}", &path);
+                        continue;
+                    }
+                },
 
                 _ => continue,
             },
@@ -283,10 +470,123 @@ fn load_images(
     image_sender.send(ImageChannel::Finished).unwrap();
 }
 
-fn load_gif(path: PathBuf) -> Option<ImageChannel> {
+fn file_info(path: &Path) -> FileInfo {
+    let metadata = std::fs::metadata(path).ok();
+    let modified = metadata.as_ref().and_then(|meta| meta.modified().ok());
+
+    FileInfo {
+        resolution: (0, 0),
+        file_size: metadata.map(|meta| meta.len()).unwrap_or(0),
+        modified,
+        month: image_month(path, modified),
+        color_converted: false,
+    }
+}
+
+/// High bit depth image as linear floats. Float formats (EXR, Radiance HDR) are
+/// already linear while 16 bit integer images are sRGB encoded.
+fn to_linear(image: DynamicImage) -> Rgba32FImage {
+    let linear = matches!(image.color(), ColorType::Rgb32F | ColorType::Rgba32F);
+    let mut pixels = image.into_rgba32f();
+
+    if !linear {
+        pixels.pixels_mut().for_each(|pixel| {
+            pixel.0[..3]
+                .iter_mut()
+                .for_each(|value| *value = hdr::srgb_to_linear(*value));
+        });
+    }
+
+    pixels
+}
+
+/// Convert an image with an embedded ICC profile to sRGB. Returns None if the
+/// image is already sRGB or the profile can't be used.
+fn convert_to_srgb(icc: &[u8], image: &DynamicImage) -> Option<DynamicImage> {
+    let input = moxcms::ColorProfile::new_from_slice(icc).ok()?;
+
+    // Only RGB profiles can be applied, grayscale and CMYK profiles are skipped
+    if input.color_space != moxcms::DataColorSpace::Rgb || is_srgb(&input) {
+        return None;
+    }
+
+    let output = moxcms::ColorProfile::new_srgb();
+    let layout = moxcms::Layout::Rgba;
+    let options = moxcms::TransformOptions::default();
+
+    match image.color() {
+        // Float formats hold linear values, which profiles don't describe
+        ColorType::Rgb32F | ColorType::Rgba32F => None,
+
+        color if color.bytes_per_pixel() > color.channel_count() => {
+            let transform = input
+                .create_transform_16bit(layout, &output, layout, options)
+                .ok()?;
+
+            let source = image.to_rgba16();
+            let mut pixels = source.clone();
+            transform.transform(&source, &mut pixels).ok()?;
+
+            Some(DynamicImage::ImageRgba16(pixels))
+        }
+
+        _ => {
+            let transform = input
+                .create_transform_8bit(layout, &output, layout, options)
+                .ok()?;
+
+            let source = image.to_rgba8();
+            let mut pixels = source.clone();
+            transform.transform(&source, &mut pixels).ok()?;
+
+            Some(DynamicImage::ImageRgba8(pixels))
+        }
+    }
+}
+
+/// Whether the profile has sRGB's primaries, so converting would change nothing
+fn is_srgb(profile: &moxcms::ColorProfile) -> bool {
+    let srgb = moxcms::ColorProfile::new_srgb();
+    let close = |a: moxcms::Xyzd, b: moxcms::Xyzd| {
+        (a.x - b.x).abs() < 0.002 && (a.y - b.y).abs() < 0.002 && (a.z - b.z).abs() < 0.002
+    };
+
+    close(profile.red_colorant, srgb.red_colorant)
+        && close(profile.green_colorant, srgb.green_colorant)
+        && close(profile.blue_colorant, srgb.blue_colorant)
+}
+
+/// Year and month from the image's EXIF data, falling back to when the file was last modified
+fn image_month(path: &Path, modified: Option<SystemTime>) -> Option<(i32, u32)> {
+    let exif_month = || {
+        let file = std::fs::File::open(path).ok()?;
+        let exif = exif::Reader::new()
+            .read_from_container(&mut std::io::BufReader::new(file))
+            .ok()?;
+
+        let field = exif.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)?;
+        let date = match &field.value {
+            exif::Value::Ascii(ascii) => exif::DateTime::from_ascii(ascii.first()?).ok()?,
+            _ => return None,
+        };
+
+        // Malformed dates like "2020:00:00" fall back to the file's time
+        match date.month {
+            1..=12 => Some((date.year as i32, date.month as u32)),
+            _ => None,
+        }
+    };
+
+    exif_month().or_else(|| {
+        let (year, month, ..) = civil_from_time(modified?);
+        Some((year, month))
+    })
+}
+
+fn load_gif(path: PathBuf, mut info: FileInfo) -> Option<ImageChannel> {
     let file = std::fs::File::open(path.clone()).ok()?;
     let reader = std::io::BufReader::new(file);
-    let gif = GifDecoder::new(reader).unwrap();
+    let gif = GifDecoder::new(reader).ok()?;
 
     let frames = gif.into_frames().collect_frames().ok()?;
 
@@ -296,6 +596,7 @@ fn load_gif(path: PathBuf) -> Option<ImageChannel> {
 
     let original_frame_width = frames[0].buffer().width();
     let original_frame_height = frames[0].buffer().height();
+    info.resolution = (original_frame_width, original_frame_height);
 
     // Shrink gifs if they are larger than they need to be
     let (frame_width, frame_height) = {
@@ -343,6 +644,7 @@ fn load_gif(path: PathBuf) -> Option<ImageChannel> {
 
             ImageChannel::Gif {
                 path,
+                info,
                 image,
                 total_frames: 1,
                 frames_per_row: 1,
@@ -386,6 +688,7 @@ fn load_gif(path: PathBuf) -> Option<ImageChannel> {
 
             ImageChannel::Gif {
                 path,
+                info,
                 image,
                 total_frames: frames.len() as u32,
                 frames_per_row,
@@ -407,34 +710,67 @@ fn sys_check_pending(storage: Res<Storage>) -> bool {
     !storage.to_spawn.is_empty()
 }
 
-fn sys_process_new_images(device: Res<Device>, queue: Res<Queue>, mut storage: ResMut<Storage>) {
+fn sys_process_new_images(
+    device: Res<Device>,
+    queue: Res<Queue>,
+    mut storage: ResMut<Storage>,
+    mut texture_pipeline: ResMut<Texture2dPipeline>,
+    mut gif_pipeline: ResMut<Gif2dPipeline>,
+) {
     loop {
         let mut hasher = ahash::AHasher::default();
 
         let texture_data = match storage.image_receiver.try_recv() {
             Ok(image) => match image {
-                ImageChannel::Image { path, image } => {
-                    let texture = texture::RawTexture::from_image(
-                        device.inner(),
-                        queue.inner(),
-                        &image,
-                        None,
-                        None,
-                    );
-
+                ImageChannel::Image {
+                    path,
+                    info,
+                    image,
+                    thumbnail,
+                    scene_referred,
+                } => {
                     let resolution = image.dimensions().into();
 
+                    // High bit depth images arrive as linear floats
+                    let texture = match image {
+                        DynamicImage::ImageRgba32F(image) => {
+                            TextureType::Hdr(HdrTexture::from_image(
+                                device.inner(),
+                                queue.inner(),
+                                &image,
+                                scene_referred,
+                                None,
+                            ))
+                        }
+                        image => TextureType::Texture(texture::RawTexture::from_image(
+                            device.inner(),
+                            queue.inner(),
+                            &image,
+                            None,
+                            None,
+                        )),
+                    };
+
                     path.hash(&mut hasher);
 
+                    texture_pipeline.add_thumbnail(
+                        device.inner(),
+                        queue.inner(),
+                        hasher.finish(),
+                        &thumbnail,
+                    );
+
                     Some(TextureData {
-                        texture: TextureType::Texture(texture),
+                        texture,
                         path,
                         resolution,
+                        info,
                     })
                 }
 
                 ImageChannel::Gif {
                     path,
+                    info,
                     image,
                     total_frames,
                     frames_per_row,
@@ -459,15 +795,14 @@ fn sys_process_new_images(device: Res<Device>, queue: Res<Queue>, mut storage: R
                         total_rows,
                         frame_size.0,
                         frame_size.1,
+                        GifFrameDelay::from_durations(&frame_delay),
                     );
 
                     Some(TextureData {
-                        texture: TextureType::Gif {
-                            gif,
-                            frames: frame_delay,
-                        },
+                        texture: TextureType::Gif(gif),
                         path,
                         resolution,
+                        info,
                     })
                 }
 
@@ -485,6 +820,16 @@ fn sys_process_new_images(device: Res<Device>, queue: Res<Queue>, mut storage: R
         if let Some(texture_data) = texture_data {
             let key = hasher.finish();
 
+            match &texture_data.texture {
+                TextureType::Texture(texture) => {
+                    texture_pipeline.add_texture(device.inner(), key, texture)
+                }
+                TextureType::Hdr(texture) => {
+                    texture_pipeline.add_hdr_texture(device.inner(), key, texture)
+                }
+                TextureType::Gif(gif) => gif_pipeline.add_gif(device.inner(), key, gif),
+            }
+
             storage.textures.insert(key, texture_data);
 
             storage.to_spawn.push(key);
@@ -493,16 +838,16 @@ fn sys_process_new_images(device: Res<Device>, queue: Res<Queue>, mut storage: R
 }
 
 fn sys_spawn_new_images(
-    device: Res<Device>,
-    texture_pipeline: Res<Texture2dPipeline>,
-    gif_pipeline: Res<Gif2dPipeline>,
     mut font_system: ResMut<TextFontSystem>,
+    settings: Res<Settings>,
 
     mut storage: ResMut<Storage>,
     mut layout: ResMut<LayoutManager>,
 
     mut image_creator: ImageCreator,
     mut vm_indexed: ViewMut<ImageIndex>,
+    mut vm_source: ViewMut<ImageSource>,
+    mut vm_caption: ViewMut<ImageCaption>,
     mut vm_text: ViewMut<Text2dBuffer>,
 ) {
     storage.to_spawn.iter().for_each(|id| {
@@ -512,52 +857,56 @@ fn sys_spawn_new_images(
 
         let meta = ImageMeta {
             texture_resolution: texture.resolution,
+            color_converted: texture.info.color_converted,
         };
 
-        let entity_id = match &texture.texture {
-            TextureType::Texture(texture) => {
-                let image = StandardImage {
-                    id: *id,
-                    instance: Texture2dInstance::new(
-                        device.inner(),
-                        &texture_pipeline,
-                        Texture2dInstanceRaw::default(),
-                        texture,
-                    ),
-                };
+        let frames = match &texture.texture {
+            TextureType::Texture(_) | TextureType::Hdr(_) => 1,
+            TextureType::Gif(gif) => gif.total_frames,
+        };
+
+        let caption = ImageCaption::new(
+            &texture.path.file_name().unwrap().to_string_lossy(),
+            &texture.info,
+            frames,
+            &settings.appearance,
+        );
 
-                image_creator.spawn_image(image, meta)
+        let entity_id = match &texture.texture {
+            TextureType::Texture(_) | TextureType::Hdr(_) => {
+                image_creator.spawn_image(StandardImage { id: *id }, meta)
             }
 
-            TextureType::Gif { gif, frames } => {
-                let gif = GifImage {
+            TextureType::Gif(_) => image_creator.spawn_gif(
+                GifImage {
                     id: *id,
-                    frame: 0,
-                    total_frames: gif.total_frames,
-                    frames_per_row: gif.frames_per_row,
-                    instance: Gif2dInstance::new(
-                        device.inner(),
-                        &gif_pipeline,
-                        Gif2dInstanceRaw::default(),
-                        gif,
-                    ),
-                };
+                    frame: settings.appearance.animate_gifs.held_frame(false),
+                },
+                meta,
+            ),
+        };
 
-                image_creator.spawn_gif(gif, frames, meta)
-            }
+        let source = ImageSource {
+            path: texture.path.clone(),
+            folder: storage.folder(&texture.path).to_path_buf(),
+            month: texture.info.month,
+            order: index,
         };
 
         image_creator.entities.add_component(
             entity_id,
-            (&mut vm_indexed, &mut vm_text),
+            (
+                &mut vm_indexed,
+                &mut vm_source,
+                &mut vm_caption,
+                &mut vm_text,
+            ),
             (
                 ImageIndex { index },
-                Text2dBuffer::new(
-                    font_system.inner_mut(),
-                    &Text2dBufferDescriptor::new_text(
-                        texture.path.file_name().unwrap().to_str().unwrap(),
-                    ),
-                ),
+                source,
+                caption,
+                // Filled in once the caption is fitted to its tile
+                Text2dBuffer::new(font_system.inner_mut(), &Text2dBufferDescriptor::default()),
             ),
         );
     });
@@ -1,5 +1,7 @@
 //====================================================================
 
+use std::time::SystemTime;
+
 //====================================================================
 
 #[derive(Clone)]
@@ -63,28 +65,138 @@ pub fn aabb_point(point: glam::Vec2, area_pos: glam::Vec2, area_size: glam::Vec2
     true
 }
 
-// pub(crate) fn aabb(
-//     pos_a: glam::Vec2,
-//     size_a: glam::Vec2,
-//     pos_b: glam::Vec2,
-//     size_b: glam::Vec2,
-// ) -> bool {
-//     let half_a = glam::vec2(size_a.x / 2., size_a.y / 2.);
-//     let half_b = glam::vec2(size_b.x / 2., size_b.y / 2.);
+pub(crate) fn aabb(
+    pos_a: glam::Vec2,
+    size_a: glam::Vec2,
+    pos_b: glam::Vec2,
+    size_b: glam::Vec2,
+) -> bool {
+    let half_a = glam::vec2(size_a.x / 2., size_a.y / 2.);
+    let half_b = glam::vec2(size_b.x / 2., size_b.y / 2.);
+
+    let a_min_x = pos_a.x - half_a.x;
+    let a_max_x = pos_a.x + half_a.x;
+
+    let b_min_x = pos_b.x - half_b.x;
+    let b_max_x = pos_b.x + half_b.x;
+
+    let a_min_y = pos_a.y - half_a.y;
+    let a_max_y = pos_a.y + half_a.y;
+
+    let b_min_y = pos_b.y - half_b.y;
+    let b_max_y = pos_b.y + half_b.y;
+
+    a_min_x <= b_max_x && a_max_x >= b_min_x && a_min_y <= b_max_y && a_max_y >= b_min_y
+}
+
+//====================================================================
+
+/// UTC year, month, day and seconds into the day.
+/// Civil from days - http://howardhinnant.github.io/date_algorithms.html
+pub(crate) fn civil_from_time(time: SystemTime) -> (i32, u32, u32, u32) {
+    let secs = match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        // Round down so part of a second before the epoch is still the day before
+        Err(e) => -(e.duration().as_secs() as i64) - (e.duration().subsec_nanos() > 0) as i64,
+    };
+
+    let days = secs.div_euclid(86_400) + 719_468;
+    let seconds = secs.rem_euclid(86_400) as u32;
+
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = match month_index < 10 {
+        true => month_index + 3,
+        false => month_index - 9,
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    (year as i32, month as u32, day as u32, seconds)
+}
+
+/// Shorten text to at most `max_chars` by replacing its middle with an ellipsis
+pub(crate) fn ellipsize_middle(text: &str, max_chars: usize) -> String {
+    let count = text.chars().count();
 
-//     let a_min_x = pos_a.x - half_a.x;
-//     let a_max_x = pos_a.x + half_a.x;
+    if count <= max_chars {
+        return text.to_string();
+    }
 
-//     let b_min_x = pos_b.x - half_b.x;
-//     let b_max_x = pos_b.x + half_b.x;
+    if max_chars == 0 {
+        return String::new();
+    }
 
-//     let a_min_y = pos_a.y - half_a.y;
-//     let a_max_y = pos_a.y + half_a.y;
+    let keep = max_chars - 1;
+    let head = keep.div_ceil(2);
+    let tail = keep - head;
 
-//     let b_min_y = pos_b.y - half_b.y;
-//     let b_max_y = pos_b.y + half_b.y;
+    text.chars()
+        .take(head)
+        .chain(std::iter::once('…'))
+        .chain(text.chars().skip(count - tail))
+        .collect()
+}
 
-//     a_min_x <= b_max_x && a_max_x >= b_min_x && a_min_y <= b_max_y && a_max_y >= b_min_y
-// }
+//====================================================================
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+
+    fn after_epoch(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn civil_epoch() {
+        assert_eq!(civil_from_time(SystemTime::UNIX_EPOCH), (1970, 1, 1, 0));
+    }
+
+    #[test]
+    fn civil_leap_day() {
+        let leap_day = 11_016 * 86_400;
+
+        assert_eq!(civil_from_time(after_epoch(leap_day)), (2000, 2, 29, 0));
+        assert_eq!(
+            civil_from_time(after_epoch(leap_day + 86_399)),
+            (2000, 2, 29, 86_399)
+        );
+        assert_eq!(
+            civil_from_time(after_epoch(leap_day + 86_400)),
+            (2000, 3, 1, 0)
+        );
+    }
+
+    #[test]
+    fn civil_year_boundary() {
+        let new_year = 10_957 * 86_400;
+
+        assert_eq!(
+            civil_from_time(after_epoch(new_year - 1)),
+            (1999, 12, 31, 86_399)
+        );
+        assert_eq!(civil_from_time(after_epoch(new_year)), (2000, 1, 1, 0));
+    }
+
+    #[test]
+    fn civil_before_epoch() {
+        let before = |duration: Duration| civil_from_time(SystemTime::UNIX_EPOCH - duration);
+
+        assert_eq!(before(Duration::from_secs(1)), (1969, 12, 31, 86_399));
+        assert_eq!(before(Duration::from_millis(500)), (1969, 12, 31, 86_399));
+        assert_eq!(
+            before(Duration::from_secs(165 * 86_400 - 73_060)),
+            (1969, 7, 20, 73_060)
+        );
+    }
+}
 
 //====================================================================
@@ -0,0 +1,312 @@
+//====================================================================
+
+use cabat::{
+    common::WindowSize,
+    renderer::text::{Text2dBuffer, Text2dBufferDescriptor, TextFontSystem},
+    runner::tools::MouseInput,
+    shipyard_tools::{prelude::*, UniqueTools},
+};
+use shipyard::{
+    AllStoragesView, EntitiesView, EntitiesViewMut, EntityId, Get, IntoIter, IntoWithId, Unique,
+    View, ViewMut,
+};
+
+use crate::{
+    adjustments::Adjustments,
+    exposure::Exposure,
+    images::{GifImage, ImageDirty, ImageMeta, ImageShown, ImageSize, Pos, StandardImage},
+    keybinds::{Action, ActionInput},
+    layout::LayoutManager,
+    playback::GifPlayback,
+    renderer::camera::UiCamera,
+    splitter::Splitter,
+    tools::aabb_point,
+};
+
+//====================================================================
+
+pub(crate) struct ViewerPlugin;
+
+impl Plugin for ViewerPlugin {
+    fn build(self, workload_builder: &WorkloadBuilder) {
+        workload_builder
+            .add_workload(Stages::Setup, sys_setup_viewer)
+            .add_workload(
+                Stages::Update,
+                (sys_navigate_viewer, sys_update_viewer_overlay).into_sequential_workload(),
+            );
+    }
+}
+
+//====================================================================
+
+#[derive(Unique)]
+pub struct Viewer {
+    fill: bool,
+    // Screen pixels per texture pixel. None fits (or fills) the pane.
+    scale: Option<f32>,
+    offset: glam::Vec2,
+    drag: Option<glam::Vec2>,
+
+    min_scale: f32,
+    max_scale: f32,
+    zoom_step: f32,
+
+    overlay_id: EntityId,
+    // Last text shaped into the overlay
+    overlay_text: String,
+}
+
+impl Viewer {
+    pub fn reset(&mut self) {
+        self.scale = None;
+        self.offset = glam::Vec2::ZERO;
+        self.drag = None;
+    }
+
+    pub fn scale(&self, pane_size: glam::Vec2, resolution: glam::Vec2) -> f32 {
+        match self.scale {
+            Some(scale) => scale,
+            None => {
+                let ratio = pane_size / resolution;
+                match self.fill {
+                    true => ratio.max_element(),
+                    false => ratio.min_element(),
+                }
+            }
+        }
+    }
+
+    /// Position and size the shown image within the viewer pane
+    pub fn place(
+        &self,
+        pane_pos: glam::Vec2,
+        pane_size: glam::Vec2,
+        meta: &ImageMeta,
+        pos: &mut Pos,
+        size: &mut ImageSize,
+    ) {
+        let resolution = glam::vec2(
+            meta.texture_resolution.width as f32,
+            meta.texture_resolution.height as f32,
+        );
+        let scale = self.scale(pane_size, resolution);
+
+        size.width = resolution.x * scale;
+        size.height = resolution.y * scale;
+
+        pos.x = pane_pos.x + self.offset.x;
+        pos.y = pane_pos.y + self.offset.y;
+    }
+}
+
+//====================================================================
+
+fn sys_setup_viewer(
+    all_storages: AllStoragesView,
+    mut entities: EntitiesViewMut,
+
+    mut font_system: ResMut<TextFontSystem>,
+    mut vm_text_buffer: ViewMut<Text2dBuffer>,
+) {
+    let overlay_id = entities.add_entity(
+        &mut vm_text_buffer,
+        Text2dBuffer::new(
+            font_system.inner_mut(),
+            &Text2dBufferDescriptor {
+                ..Default::default()
+            },
+        ),
+    );
+
+    all_storages.add_unique(Viewer {
+        fill: false,
+        scale: None,
+        offset: glam::Vec2::ZERO,
+        drag: None,
+
+        min_scale: 0.05,
+        max_scale: 32.,
+        zoom_step: 1.15,
+
+        overlay_id,
+        overlay_text: String::new(),
+    });
+}
+
+fn sys_navigate_viewer(
+    window_size: Res<WindowSize>,
+    layout: Res<LayoutManager>,
+    mut viewer: ResMut<Viewer>,
+    ui_camera: Res<UiCamera>,
+    splitter: Res<Splitter>,
+    playback: Res<GifPlayback>,
+    exposure: Res<Exposure>,
+
+    actions: ActionInput,
+    mouse: Res<MouseInput>,
+
+    entities: EntitiesView,
+    v_shown: View<ImageShown>,
+    v_meta: View<ImageMeta>,
+    mut vm_pos: ViewMut<Pos>,
+    mut vm_size: ViewMut<ImageSize>,
+    mut vm_dirty: ViewMut<ImageDirty>,
+) {
+    if !layout.selected() {
+        if viewer.drag.is_some() {
+            viewer.drag = None;
+        }
+        return;
+    }
+
+    let (id, meta) = match (&v_shown, &v_meta).iter().with_id().next() {
+        Some((id, (_, meta))) => (id, meta),
+        None => return,
+    };
+
+    let (pane_pos, pane_size) = layout.viewer_pane(&window_size);
+    let resolution = glam::vec2(
+        meta.texture_resolution.width as f32,
+        meta.texture_resolution.height as f32,
+    );
+    let scale = viewer.scale(pane_size, resolution);
+
+    let mouse_pos = ui_camera.raw.screen_to_camera(mouse.screen_pos());
+    let over_pane = aabb_point(mouse_pos, pane_pos, pane_size);
+
+    let mut changed = false;
+
+    // 1:1 pixel zoom
+    if actions.just_pressed(Action::ViewerActualSize) {
+        viewer.scale = Some(1.);
+        viewer.offset = glam::Vec2::ZERO;
+        changed = true;
+    }
+
+    // Toggle between fitting and filling the pane
+    if actions.just_pressed(Action::ViewerToggleFill) {
+        viewer.fill = !viewer.fill;
+        viewer.reset();
+        changed = true;
+    }
+
+    // Zoom around the cursor
+    let wheel = mouse.scroll().y;
+    if over_pane && wheel != 0. {
+        let new_scale =
+            (scale * viewer.zoom_step.powf(wheel)).clamp(viewer.min_scale, viewer.max_scale);
+
+        let center = pane_pos + viewer.offset;
+        let image_point = (mouse_pos - center) / scale;
+        let new_center = mouse_pos - image_point * new_scale;
+
+        viewer.offset = new_center - pane_pos;
+        viewer.scale = Some(new_scale);
+        changed = true;
+    }
+
+    // Click and drag to pan, leaving the divider, scrub bar and exposure slider to their owners
+    let over_controls = splitter.contains(&layout, &window_size, mouse_pos)
+        || playback.contains(&layout, &window_size, mouse_pos)
+        || exposure.contains(&layout, &window_size, mouse_pos);
+
+    if over_pane && !over_controls && actions.just_pressed(Action::ViewerPan) {
+        viewer.drag = Some(mouse_pos);
+    }
+
+    if let Some(last) = viewer.drag {
+        match actions.pressed(Action::ViewerPan) {
+            true => {
+                if last != mouse_pos {
+                    viewer.offset += mouse_pos - last;
+                    viewer.drag = Some(mouse_pos);
+                    changed = true;
+                }
+            }
+            false => viewer.drag = None,
+        }
+    }
+
+    if !changed {
+        return;
+    }
+
+    let (mut pos, mut size) = (&mut vm_pos, &mut vm_size).get(id).unwrap();
+    viewer.place(pane_pos, pane_size, meta, &mut pos, &mut size);
+
+    entities.add_component(id, &mut vm_dirty, ImageDirty);
+}
+
+fn sys_update_viewer_overlay(
+    window_size: Res<WindowSize>,
+    layout: Res<LayoutManager>,
+    mut viewer: ResMut<Viewer>,
+    adjustments: Res<Adjustments>,
+
+    mut font_system: ResMut<TextFontSystem>,
+    mut vm_text_buffer: ViewMut<Text2dBuffer>,
+
+    v_shown: View<ImageShown>,
+    v_meta: View<ImageMeta>,
+    v_image: View<StandardImage>,
+    v_gif: View<GifImage>,
+) {
+    let (pane_pos, pane_size) = layout.viewer_pane(&window_size);
+
+    let text = match (
+        layout.selected(),
+        (&v_shown, &v_meta).iter().with_id().next(),
+    ) {
+        (true, Some((id, (_, meta)))) => {
+            let resolution = glam::vec2(
+                meta.texture_resolution.width as f32,
+                meta.texture_resolution.height as f32,
+            );
+
+            let texture_id = (&v_image)
+                .get(id)
+                .map(|image| image.id)
+                .or_else(|_| (&v_gif).get(id).map(|gif| gif.id))
+                .ok();
+
+            let adjusted = texture_id
+                .map(|texture_id| adjustments.get(texture_id).summary())
+                .unwrap_or_default();
+
+            // Flag images shown through their colour profile
+            format!(
+                "{:.0}%{}{}{}",
+                viewer.scale(pane_size, resolution) * 100.,
+                match meta.color_converted {
+                    true => "   ICC",
+                    false => "",
+                },
+                match adjusted.is_empty() {
+                    true => "",
+                    false => "   ",
+                },
+                adjusted
+            )
+        }
+        _ => String::new(),
+    };
+
+    let mut buffer = (&mut vm_text_buffer).get(viewer.overlay_id).unwrap();
+
+    // Top left corner of the pane in screen space
+    buffer.pos.0 = window_size.width_f32() / 2. + pane_pos.x - pane_size.x / 2. + 12.;
+    buffer.pos.1 = 12.;
+
+    buffer.bounds.top = 0;
+    buffer.bounds.bottom = window_size.height() as i32;
+    buffer.bounds.left = 0;
+    buffer.bounds.right = window_size.width() as i32;
+
+    if viewer.overlay_text != text {
+        buffer.set_text(font_system.inner_mut(), &text);
+        viewer.overlay_text = text;
+    }
+}
+
+//====================================================================